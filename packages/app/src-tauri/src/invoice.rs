@@ -1,14 +1,77 @@
+use printpdf::image_crate::GenericImageView;
 use printpdf::*;
 use std::fs::{self, File};
 use std::io::BufWriter;
 use std::path::PathBuf;
+use crate::money::Cents;
+use crate::ProtimerError;
 
 #[derive(Debug)]
 pub struct InvoiceEntry {
     pub date: String,
+    pub description: Option<String>,
     pub hours: f64,
     pub rate: f64,
-    pub amount: f64,
+    pub amount: Cents,
+}
+
+// A run of rows in the table. Combined invoices have one per project, headed by the
+// project name and followed by its subtotal; single-project invoices have one untitled section.
+#[derive(Debug)]
+pub struct InvoiceSection {
+    pub title: Option<String>,
+    pub entries: Vec<InvoiceEntry>,
+}
+
+// Flat discount (negative) or surcharge (positive) added to the subtotal before tax
+#[derive(Debug)]
+pub struct InvoiceAdjustment {
+    pub label: String,
+    pub amount: Cents,
+}
+
+// One tax in the totals block, e.g. GST and PST as separate lines
+#[derive(Debug)]
+pub struct TaxLine {
+    pub name: String,
+    pub rate: f64,
+    pub amount: Cents,
+}
+
+// Paper size of the generated PDF. The layout keeps 20mm side margins on either size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageSize {
+    A4,
+    Letter,
+}
+
+impl PageSize {
+    // Names stored in business_info.pageSize
+    pub fn from_name(name: &str) -> Option<PageSize> {
+        match name {
+            "A4" => Some(PageSize::A4),
+            "Letter" => Some(PageSize::Letter),
+            _ => None,
+        }
+    }
+
+    // (width, height) in mm
+    fn dimensions_mm(self) -> (f32, f32) {
+        match self {
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::Letter => (215.9, 279.4),
+        }
+    }
+}
+
+// Direction each entry's duration is rounded to the billing increment. Nearest breaks ties upward.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundingMode {
+    #[default]
+    Up,
+    Nearest,
+    Down,
 }
 
 #[derive(Debug)]
@@ -17,30 +80,306 @@ pub struct InvoiceData {
     pub invoice_date: String,
     pub business_name: String,
     pub business_email: Option<String>,
+    pub logo_path: Option<String>,
+    pub font_path: Option<String>,
+    pub currency: String,
+    pub client_name: String,
+    pub client_email: Option<String>,
+    pub client_address: Option<String>,
     pub project_name: String,
-    pub entries: Vec<InvoiceEntry>,
-    pub subtotal: f64,
-    pub tax_rate: f64,
-    pub tax_amount: f64,
-    pub total: f64,
+    // Client's reference for the work, e.g. a PO number
+    pub header_note: Option<String>,
+    pub sections: Vec<InvoiceSection>,
+    pub adjustments: Vec<InvoiceAdjustment>,
+    pub billing_increment_minutes: Option<u32>,
+    pub rounding_mode: RoundingMode,
+    pub subtotal: Cents,
+    pub taxes: Vec<TaxLine>,
+    // The subtotal already contains the taxes, so the total equals the subtotal
+    pub tax_inclusive: bool,
+    pub total: Cents,
+    pub notes: Option<String>,
+    pub payment_terms: Option<String>,
+    pub due_date: Option<String>,
+    pub page_size: PageSize,
+    // When the invoice was issued (ms since epoch); used for every date in the PDF metadata
+    pub created_at: i64,
+    // Fixed PDF document ID, so regenerating an invoice doesn't introduce a new random one
+    pub document_id: String,
+}
+
+// Format an amount with its currency symbol, placed per the currency's convention.
+// Unknown currencies fall back to the 3-letter code after the amount.
+pub fn format_money(amount: Cents, currency: &str) -> String {
+    match currency {
+        "USD" => format!("${}", amount),
+        "GBP" => format!("\u{00A3}{}", amount),
+        "EUR" => format!("{} \u{20AC}", amount),
+        code => format!("{} {}", amount, code),
+    }
+}
+
+// Box in the top-right corner of page one that the logo is scaled to fit
+const LOGO_MAX_WIDTH_MM: f32 = 50.0;
+const LOGO_MAX_HEIGHT_MM: f32 = 20.0;
+const LOGO_DPI: f32 = 300.0;
+
+// Draw the business logo in the header. An unreadable or undecodable image is skipped
+// so a bad path falls back to the text-only header instead of failing the invoice.
+fn draw_logo(layer: &PdfLayerReference, logo_path: &str, right_mm: f32, top_mm: f32) {
+    let image = match image_crate::open(logo_path) {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!("Skipping invoice logo {}: {}", logo_path, e);
+            return;
+        }
+    };
+
+    let (width_px, height_px) = image.dimensions();
+    if width_px == 0 || height_px == 0 {
+        return;
+    }
+
+    // Natural size at LOGO_DPI, then scaled to fit the box
+    let width_mm = width_px as f32 / LOGO_DPI * 25.4;
+    let height_mm = height_px as f32 / LOGO_DPI * 25.4;
+    let scale = (LOGO_MAX_WIDTH_MM / width_mm).min(LOGO_MAX_HEIGHT_MM / height_mm);
+
+    Image::from_dynamic_image(&image).add_to_layer(
+        layer.clone(),
+        ImageTransform {
+            translate_x: Some(Mm(right_mm - width_mm * scale)),
+            translate_y: Some(Mm(top_mm - height_mm * scale)),
+            scale_x: Some(scale),
+            scale_y: Some(scale),
+            dpi: Some(LOGO_DPI),
+            ..Default::default()
+        },
+    );
+}
+
+// System fonts with wide Unicode coverage, tried when no font is configured but the
+// invoice has text Helvetica can't render
+const DEFAULT_UNICODE_FONTS: [&str; 3] = [
+    "/Library/Fonts/Arial Unicode.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+];
+
+// Whether any user-entered text falls outside Latin-1
+fn needs_unicode_font(data: &InvoiceData) -> bool {
+    let mut texts = vec![&data.business_name, &data.client_name, &data.project_name];
+    texts.extend(data.client_address.iter());
+    texts.extend(data.header_note.iter());
+    texts.extend(data.notes.iter());
+    texts.extend(data.payment_terms.iter());
+    for section in &data.sections {
+        texts.extend(section.title.iter());
+        texts.extend(section.entries.iter().filter_map(|e| e.description.as_ref()));
+    }
+    texts.iter().any(|t| t.chars().any(|c| c as u32 > 0xFF))
+}
+
+// Roughly what fits between the page margins at 9pt
+const FOOTER_WRAP_CHARS: usize = 95;
+
+// Greedy word wrap; explicit line breaks in the text are kept
+fn wrap_text(text: &str, max_chars: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > max_chars {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// Right page margin, matching the 20mm left margin
+const MARGIN_MM: f32 = 20.0;
+// Right edges of the numeric columns, measured back from the right margin; totals line up under Amount
+const HOURS_FROM_RIGHT_MM: f32 = 45.0;
+const RATE_FROM_RIGHT_MM: f32 = 22.0;
+// Left edge of the totals labels and the invoice date, back from the right margin
+const TOTALS_FROM_RIGHT_MM: f32 = 40.0;
+const DATE_FROM_RIGHT_MM: f32 = 50.0;
+// Table rows stop this far above the bottom edge and continue on a new page
+const TABLE_BOTTOM_MM: f32 = 50.0;
+// Lowest baseline for totals and footer text
+const PAGE_BOTTOM_MM: f32 = 20.0;
+// First baseline on continuation pages, down from the top edge
+const CONTINUED_TOP_MM: f32 = 20.0;
+
+// Advance width of a character in Helvetica / Helvetica-Bold, in 1/1000 em (from the AFM metrics).
+// Characters without an entry use the digit width, which is close enough for alignment.
+fn helvetica_char_width(c: char, bold: bool) -> u32 {
+    match c {
+        '0'..='9' | '$' | '\u{00A3}' | '\u{20AC}' | '#' => 556,
+        '.' | ',' | ' ' => 278,
+        ':' | ';' => if bold { 333 } else { 278 },
+        '-' | '(' | ')' => 333,
+        '%' => 889,
+        'A' | 'B' | 'K' => if bold { 722 } else { 667 },
+        'E' | 'P' | 'S' | 'V' | 'X' | 'Y' => 667,
+        'C' | 'D' | 'H' | 'N' | 'R' | 'U' => 722,
+        'F' | 'T' | 'Z' => 611,
+        'G' | 'O' | 'Q' => 778,
+        'I' => 278,
+        'J' => if bold { 556 } else { 500 },
+        'L' => if bold { 611 } else { 556 },
+        'M' => 833,
+        'W' => 944,
+        'a' | 'e' => 556,
+        'b' | 'd' | 'g' | 'h' | 'n' | 'o' | 'p' | 'q' | 'u' => if bold { 611 } else { 556 },
+        'c' | 'k' | 's' | 'v' | 'x' | 'y' => if bold { 556 } else { 500 },
+        'f' | 't' => if bold { 333 } else { 278 },
+        'i' | 'j' | 'l' => if bold { 278 } else { 222 },
+        'm' => if bold { 889 } else { 833 },
+        'r' => if bold { 389 } else { 333 },
+        'w' => if bold { 778 } else { 722 },
+        'z' => 500,
+        _ => 556,
+    }
+}
+
+fn text_width_mm(text: &str, font_size: f32, bold: bool) -> f32 {
+    let units: u32 = text.chars().map(|c| helvetica_char_width(c, bold)).sum();
+    // 1pt = 0.3528mm
+    units as f32 / 1000.0 * font_size * 0.3528
+}
+
+// Draw text so it ends at right_mm, keeping numbers of different widths aligned on the right
+fn use_text_right(
+    layer: &PdfLayerReference,
+    text: &str,
+    font_size: f32,
+    right_mm: f32,
+    y_mm: f32,
+    font: &IndirectFontRef,
+    bold: bool,
+) {
+    let x = right_mm - text_width_mm(text, font_size, bold);
+    layer.use_text(text, font_size, Mm(x), Mm(y_mm), font);
+}
+
+// The table's column headings between two rules, at the top of the table and again on each
+// continuation page. Returns the baseline for the first row.
+fn draw_table_header(
+    layer: &PdfLayerReference,
+    y_mm: f32,
+    amount_right: f32,
+    has_descriptions: bool,
+    font_bold: &IndirectFontRef,
+) -> f32 {
+    let rule = |y: f32| Line {
+        points: vec![
+            (Point::new(Mm(20.0), Mm(y)), false),
+            (Point::new(Mm(amount_right), Mm(y)), false),
+        ],
+        is_closed: false,
+    };
+    let mut y_position = y_mm;
+    layer.add_line(rule(y_position));
+
+    y_position -= 5.0;
+
+    if has_descriptions {
+        layer.use_text("Date", 10.0, Mm(20.0), Mm(y_position), font_bold);
+        layer.use_text("Description", 10.0, Mm(50.0), Mm(y_position), font_bold);
+    } else {
+        layer.use_text("Period", 10.0, Mm(20.0), Mm(y_position), font_bold);
+    }
+    use_text_right(layer, "Hours", 10.0, amount_right - HOURS_FROM_RIGHT_MM, y_position, font_bold, true);
+    use_text_right(layer, "Rate", 10.0, amount_right - RATE_FROM_RIGHT_MM, y_position, font_bold, true);
+    use_text_right(layer, "Amount", 10.0, amount_right, y_position, font_bold, true);
+
+    y_position -= 5.0;
+    layer.add_line(rule(y_position));
+
+    y_position - 6.0
 }
 
 pub fn generate_invoice_pdf(data: InvoiceData, output_path: PathBuf) -> Result<String, String> {
     // Create PDF document
+    let (page_width, page_height) = data.page_size.dimensions_mm();
     let (doc, page1, layer1) = PdfDocument::new(
         format!("Invoice #{}", data.invoice_number),
-        Mm(210.0),  // A4 width
-        Mm(297.0),  // A4 height
+        Mm(page_width),
+        Mm(page_height),
         "Layer 1",
     );
 
-    let current_layer = doc.get_page(page1).get_layer(layer1);
+    // Metadata comes from the invoice alone rather than the clock, so identical inputs give identical output
+    let issued = OffsetDateTime::from_unix_timestamp_nanos(data.created_at as i128 * 1_000_000)
+        .map_err(|e| format!("Invalid invoice date: {}", e))?;
+    let doc = doc
+        .with_author(data.business_name.clone())
+        .with_subject(data.invoice_number.clone())
+        .with_creator("ProTimer")
+        .with_creation_date(issued)
+        .with_mod_date(issued)
+        .with_metadata_date(issued)
+        .with_document_id(data.document_id.clone());
+
+    // Horizontal positions follow the right margin so the layout fits either paper width
+    let amount_right = page_width - MARGIN_MM;
+    let hours_right = amount_right - HOURS_FROM_RIGHT_MM;
+    let rate_right = amount_right - RATE_FROM_RIGHT_MM;
+    let totals_x = amount_right - TOTALS_FROM_RIGHT_MM;
+
+    let mut current_layer = doc.get_page(page1).get_layer(layer1);
+    // Start another page of the same size, headed with the invoice number; returns its layer and first baseline
+    let new_page = |font: &IndirectFontRef| {
+        let (page, layer) = doc.add_page(Mm(page_width), Mm(page_height), "Layer 1");
+        let layer = doc.get_page(page).get_layer(layer);
+        let y = page_height - CONTINUED_TOP_MM;
+        layer.use_text(format!("Invoice {} (continued)", data.invoice_number), 9.0, Mm(20.0), Mm(y), font);
+        (layer, y - 10.0)
+    };
 
     // Load fonts
     let font_bold = doc.add_builtin_font(BuiltinFont::HelveticaBold).map_err(|e| e.to_string())?;
     let font_regular = doc.add_builtin_font(BuiltinFont::Helvetica).map_err(|e| e.to_string())?;
 
-    let mut y_position = 270.0; // Start from top (A4 is 297mm height)
+    // User-entered text (names, descriptions) may need glyphs outside Latin-1. Use the
+    // configured TrueType font for it, falling back to Helvetica if none is set or it fails to load.
+    let font_path = data.font_path.clone().or_else(|| {
+        if needs_unicode_font(&data) {
+            DEFAULT_UNICODE_FONTS
+                .iter()
+                .find(|p| std::path::Path::new(p).exists())
+                .map(|p| p.to_string())
+        } else {
+            None
+        }
+    });
+    let font_text = match font_path.as_deref().map(|path| (path, File::open(path))) {
+        Some((path, Ok(file))) => match doc.add_external_font(file) {
+            Ok(font) => font,
+            Err(e) => {
+                eprintln!("Falling back to builtin font, failed to load {}: {}", path, e);
+                font_regular.clone()
+            }
+        },
+        Some((path, Err(e))) => {
+            eprintln!("Falling back to builtin font, failed to open {}: {}", path, e);
+            font_regular.clone()
+        }
+        None => font_regular.clone(),
+    };
+
+    let mut y_position = page_height - 27.0; // Start from top
+
+    if let Some(ref logo_path) = data.logo_path {
+        draw_logo(&current_layer, logo_path, amount_right, page_height - 10.0);
+    }
 
     // Header - Invoice Title
     current_layer.use_text(
@@ -57,7 +396,7 @@ pub fn generate_invoice_pdf(data: InvoiceData, output_path: PathBuf) -> Result<S
     current_layer.use_text(
         format!("Date: {}", data.invoice_date),
         10.0,
-        Mm(140.0),
+        Mm(amount_right - DATE_FROM_RIGHT_MM),
         Mm(y_position),
         &font_regular,
     );
@@ -68,7 +407,7 @@ pub fn generate_invoice_pdf(data: InvoiceData, output_path: PathBuf) -> Result<S
     current_layer.use_text("FROM:", 11.0, Mm(20.0), Mm(y_position), &font_bold);
     y_position -= 6.0;
 
-    current_layer.use_text(&data.business_name, 10.0, Mm(20.0), Mm(y_position), &font_regular);
+    current_layer.use_text(&data.business_name, 10.0, Mm(20.0), Mm(y_position), &font_text);
     y_position -= 5.0;
 
     if let Some(ref email) = data.business_email {
@@ -80,96 +419,198 @@ pub fn generate_invoice_pdf(data: InvoiceData, output_path: PathBuf) -> Result<S
 
     y_position -= 10.0;
 
-    // Client info (to) - using project name
+    // Client info (to)
     current_layer.use_text("BILL TO:", 11.0, Mm(20.0), Mm(y_position), &font_bold);
     y_position -= 6.0;
 
-    current_layer.use_text(&data.project_name, 10.0, Mm(20.0), Mm(y_position), &font_regular);
+    current_layer.use_text(&data.client_name, 10.0, Mm(20.0), Mm(y_position), &font_text);
     y_position -= 5.0;
 
-    y_position -= 5.0;
+    if let Some(ref email) = data.client_email {
+        current_layer.use_text(email, 10.0, Mm(20.0), Mm(y_position), &font_regular);
+        y_position -= 5.0;
+    }
 
-    // Table header
-    let line = Line {
-        points: vec![
-            (Point::new(Mm(20.0), Mm(y_position)), false),
-            (Point::new(Mm(190.0), Mm(y_position)), false),
-        ],
-        is_closed: false,
-    };
-    current_layer.add_line(line);
+    if let Some(ref address) = data.client_address {
+        for line in address.lines().filter(|l| !l.trim().is_empty()) {
+            current_layer.use_text(line.trim(), 10.0, Mm(20.0), Mm(y_position), &font_text);
+            y_position -= 5.0;
+        }
+    }
 
-    y_position -= 5.0;
+    // Name the project separately when billing a named client
+    if data.client_name != data.project_name {
+        current_layer.use_text(
+            format!("Project: {}", data.project_name),
+            10.0,
+            Mm(20.0),
+            Mm(y_position),
+            &font_text,
+        );
+        y_position -= 5.0;
+    }
 
-    current_layer.use_text("Period", 10.0, Mm(20.0), Mm(y_position), &font_bold);
-    current_layer.use_text("Hours", 10.0, Mm(130.0), Mm(y_position), &font_bold);
-    current_layer.use_text("Rate", 10.0, Mm(155.0), Mm(y_position), &font_bold);
-    current_layer.use_text("Amount", 10.0, Mm(175.0), Mm(y_position), &font_bold);
+    if let Some(ref note) = data.header_note {
+        current_layer.use_text(format!("Ref: {}", note), 10.0, Mm(20.0), Mm(y_position), &font_text);
+        y_position -= 5.0;
+    }
 
     y_position -= 5.0;
 
-    let line = Line {
-        points: vec![
-            (Point::new(Mm(20.0), Mm(y_position)), false),
-            (Point::new(Mm(190.0), Mm(y_position)), false),
-        ],
-        is_closed: false,
-    };
-    current_layer.add_line(line);
+    // Itemized invoices carry a description per row
+    let has_descriptions = data.sections.iter().flat_map(|s| &s.entries).any(|e| e.description.is_some());
 
-    y_position -= 6.0;
+    // Table header
+    y_position = draw_table_header(&current_layer, y_position, amount_right, has_descriptions, &font_bold);
+
+    // Every row is drawn so the lines add up to the totals; rows that don't fit continue on a
+    // new page under the same headings
+    let continue_table = |layer: &mut PdfLayerReference, y: &mut f32| {
+        if *y < TABLE_BOTTOM_MM {
+            let (next_layer, top) = new_page(&font_regular);
+            *y = draw_table_header(&next_layer, top, amount_right, has_descriptions, &font_bold);
+            *layer = next_layer;
+        }
+    };
 
     // Entries
-    for entry in &data.entries {
-        if y_position < 50.0 {
-            // Need new page
-            // For simplicity, we'll just stop here
-            // In production, you'd create a new page
-            break;
+    for section in &data.sections {
+        if let Some(ref title) = section.title {
+            continue_table(&mut current_layer, &mut y_position);
+            current_layer.use_text(title, 10.0, Mm(20.0), Mm(y_position), &font_text);
+            y_position -= 5.0;
+        }
+
+        for entry in &section.entries {
+            continue_table(&mut current_layer, &mut y_position);
+
+            current_layer.use_text(&entry.date, 9.0, Mm(20.0), Mm(y_position), &font_regular);
+            if let Some(ref description) = entry.description {
+                // Truncate so long descriptions don't run into the Hours column
+                let text: String = if description.chars().count() > 45 {
+                    format!("{}...", description.chars().take(42).collect::<String>())
+                } else {
+                    description.clone()
+                };
+                current_layer.use_text(text, 9.0, Mm(50.0), Mm(y_position), &font_text);
+            }
+            use_text_right(&current_layer, &format!("{:.2}", entry.hours), 9.0, hours_right, y_position, &font_regular, false);
+            use_text_right(&current_layer, &format_money(Cents::from_amount(entry.rate), &data.currency), 9.0, rate_right, y_position, &font_regular, false);
+            use_text_right(&current_layer, &format_money(entry.amount, &data.currency), 9.0, amount_right, y_position, &font_regular, false);
+
+            y_position -= 5.0;
+        }
+
+        // Per-project subtotal under each titled section
+        if section.title.is_some() {
+            continue_table(&mut current_layer, &mut y_position);
+            let section_total: Cents = section.entries.iter().map(|e| e.amount).sum();
+            current_layer.use_text("Project subtotal:", 9.0, Mm(totals_x), Mm(y_position), &font_regular);
+            use_text_right(&current_layer, &format_money(section_total, &data.currency), 9.0, amount_right, y_position, &font_bold, true);
+            y_position -= 7.0;
         }
+    }
 
-        current_layer.use_text(&entry.date, 9.0, Mm(20.0), Mm(y_position), &font_regular);
-        current_layer.use_text(format!("{:.2}", entry.hours), 9.0, Mm(130.0), Mm(y_position), &font_regular);
-        current_layer.use_text(format!("${:.2}", entry.rate), 9.0, Mm(155.0), Mm(y_position), &font_regular);
-        current_layer.use_text(format!("${:.2}", entry.amount), 9.0, Mm(175.0), Mm(y_position), &font_regular);
+    // Adjustments follow the time rows, with no hours or rate
+    for adjustment in &data.adjustments {
+        continue_table(&mut current_layer, &mut y_position);
+
+        current_layer.use_text(&adjustment.label, 9.0, Mm(20.0), Mm(y_position), &font_text);
+        use_text_right(&current_layer, &format_money(adjustment.amount, &data.currency), 9.0, amount_right, y_position, &font_regular, false);
 
         y_position -= 5.0;
     }
 
     y_position -= 5.0;
 
+    // Move the totals to a new page rather than running them off the bottom of this one
+    let totals_height = 27.0 + 6.0 * data.taxes.len() as f32;
+    if y_position - totals_height < PAGE_BOTTOM_MM {
+        (current_layer, y_position) = new_page(&font_regular);
+    }
+
     // Bottom line
     let line = Line {
         points: vec![
             (Point::new(Mm(20.0), Mm(y_position)), false),
-            (Point::new(Mm(190.0), Mm(y_position)), false),
+            (Point::new(Mm(amount_right), Mm(y_position)), false),
         ],
         is_closed: false,
     };
     current_layer.add_line(line);
 
+    // Note the billing increment under the table
+    if let Some(minutes) = data.billing_increment_minutes {
+        y_position -= 4.0;
+        let direction = match data.rounding_mode {
+            RoundingMode::Up => "up to",
+            RoundingMode::Nearest => "to",
+            RoundingMode::Down => "down to",
+        };
+        current_layer.use_text(
+            format!("Each entry rounded {} the nearest {} minutes", direction, minutes),
+            8.0,
+            Mm(20.0),
+            Mm(y_position),
+            &font_regular,
+        );
+    }
+
     y_position -= 10.0;
 
     // Totals (right aligned)
-    current_layer.use_text("Subtotal:", 10.0, Mm(150.0), Mm(y_position), &font_regular);
-    current_layer.use_text(format!("${:.2}", data.subtotal), 10.0, Mm(170.0), Mm(y_position), &font_regular);
+    let subtotal_label = if data.tax_inclusive && !data.taxes.is_empty() { "Subtotal (incl. tax):" } else { "Subtotal:" };
+    current_layer.use_text(subtotal_label, 10.0, Mm(totals_x), Mm(y_position), &font_regular);
+    use_text_right(&current_layer, &format_money(data.subtotal, &data.currency), 10.0, amount_right, y_position, &font_regular, false);
 
-    if data.tax_rate > 0.0 {
+    for tax in &data.taxes {
         y_position -= 6.0;
+        let label = if data.tax_inclusive {
+            format!("Includes {} ({}%):", tax.name, tax.rate)
+        } else {
+            format!("{} ({}%):", tax.name, tax.rate)
+        };
         current_layer.use_text(
-            format!("Tax ({}%):", data.tax_rate),
+            label,
             10.0,
-            Mm(150.0),
+            Mm(totals_x),
             Mm(y_position),
             &font_regular,
         );
-        current_layer.use_text(format!("${:.2}", data.tax_amount), 10.0, Mm(170.0), Mm(y_position), &font_regular);
+        use_text_right(&current_layer, &format_money(tax.amount, &data.currency), 10.0, amount_right, y_position, &font_regular, false);
     }
 
     y_position -= 8.0;
 
-    current_layer.use_text("TOTAL:", 11.0, Mm(150.0), Mm(y_position), &font_bold);
-    current_layer.use_text(format!("${:.2}", data.total), 11.0, Mm(170.0), Mm(y_position), &font_bold);
+    current_layer.use_text("TOTAL:", 11.0, Mm(totals_x), Mm(y_position), &font_bold);
+    use_text_right(&current_layer, &format_money(data.total, &data.currency), 11.0, amount_right, y_position, &font_bold, true);
+
+    // Footer: due date, payment terms and notes below the totals
+    if let Some(ref due_date) = data.due_date {
+        if y_position - 12.0 < PAGE_BOTTOM_MM {
+            (current_layer, y_position) = new_page(&font_regular);
+        }
+        y_position -= 12.0;
+        current_layer.use_text(format!("Payment due: {}", due_date), 10.0, Mm(20.0), Mm(y_position), &font_bold);
+    }
+
+    for (heading, text) in [("PAYMENT TERMS:", &data.payment_terms), ("NOTES:", &data.notes)] {
+        if let Some(text) = text {
+            if y_position - 15.0 < PAGE_BOTTOM_MM {
+                (current_layer, y_position) = new_page(&font_regular);
+            }
+            y_position -= 10.0;
+            current_layer.use_text(heading, 10.0, Mm(20.0), Mm(y_position), &font_bold);
+            for line in wrap_text(text, FOOTER_WRAP_CHARS) {
+                // Carry long text onto a new page rather than drawing off this one
+                if y_position - 5.0 < PAGE_BOTTOM_MM {
+                    (current_layer, y_position) = new_page(&font_regular);
+                }
+                y_position -= 5.0;
+                current_layer.use_text(line, 9.0, Mm(20.0), Mm(y_position), &font_text);
+            }
+        }
+    }
 
     // Save PDF
     let file = File::create(&output_path).map_err(|e| format!("Failed to create file: {}", e))?;
@@ -179,19 +620,18 @@ pub fn generate_invoice_pdf(data: InvoiceData, output_path: PathBuf) -> Result<S
     Ok(output_path.to_string_lossy().to_string())
 }
 
-pub fn get_invoices_dir() -> PathBuf {
-    let home = dirs::home_dir().expect("Could not find home directory");
-    let protimer_dir = home.join(".protimer").join("invoices");
+pub fn get_invoices_dir() -> Result<PathBuf, ProtimerError> {
+    let protimer_dir = crate::get_data_dir()?.join("invoices");
 
     if !protimer_dir.exists() {
-        fs::create_dir_all(&protimer_dir).expect("Failed to create invoices directory");
+        fs::create_dir_all(&protimer_dir).map_err(|e| ProtimerError::Io(format!("Failed to create invoices directory: {}", e)))?;
     }
 
-    protimer_dir
+    Ok(protimer_dir)
 }
 
-pub fn get_project_invoices_dir(project_name: &str) -> PathBuf {
-    let invoices_dir = get_invoices_dir();
+pub fn get_project_invoices_dir(project_name: &str) -> Result<PathBuf, ProtimerError> {
+    let invoices_dir = get_invoices_dir()?;
 
     // Sanitize project name for filesystem (replace invalid chars)
     let safe_name = project_name
@@ -205,8 +645,8 @@ pub fn get_project_invoices_dir(project_name: &str) -> PathBuf {
     let project_dir = invoices_dir.join(safe_name);
 
     if !project_dir.exists() {
-        fs::create_dir_all(&project_dir).expect("Failed to create project invoices directory");
+        fs::create_dir_all(&project_dir).map_err(|e| ProtimerError::Io(format!("Failed to create project invoices directory: {}", e)))?;
     }
 
-    project_dir
+    Ok(project_dir)
 }
@@ -1,28 +1,156 @@
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, DatabaseName, OpenFlags, OptionalExtension, params};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::SystemTime;
-use tauri::{State, Emitter};
-use std::os::unix::fs::PermissionsExt;
+use tauri::{State, Emitter, Manager};
 use notify::{Watcher, RecursiveMode, Event, EventKind};
-use std::sync::mpsc::channel;
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::time::{Duration, Instant};
 
 mod invoice;
+mod money;
+
+use money::Cents;
+
+// Error returned by commands. Serializes as { kind, message } so the frontend can
+// branch on the kind and still show the message.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum ProtimerError {
+    NotFound(String),
+    Validation(String),
+    // The request is valid but needs confirmation, e.g. a project path nested in another
+    Conflict(String),
+    Database(String),
+    Io(String),
+    PdfGeneration(String),
+}
+
+impl std::fmt::Display for ProtimerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtimerError::NotFound(message)
+            | ProtimerError::Validation(message)
+            | ProtimerError::Conflict(message)
+            | ProtimerError::Database(message)
+            | ProtimerError::Io(message)
+            | ProtimerError::PdfGeneration(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ProtimerError {}
+
+impl From<rusqlite::Error> for ProtimerError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => ProtimerError::NotFound(e.to_string()),
+            _ => ProtimerError::Database(e.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for ProtimerError {
+    fn from(e: std::io::Error) -> Self {
+        ProtimerError::Io(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for ProtimerError {
+    fn from(e: serde_json::Error) -> Self {
+        ProtimerError::Validation(e.to_string())
+    }
+}
+
+// Plain messages from validation checks (`ok_or("...")?`)
+impl From<&str> for ProtimerError {
+    fn from(message: &str) -> Self {
+        ProtimerError::Validation(message.to_string())
+    }
+}
+
+impl From<String> for ProtimerError {
+    fn from(message: String) -> Self {
+        ProtimerError::Validation(message)
+    }
+}
 
 // Cache for activity log
 struct ActivityCache {
     entries: Arc<Vec<ActivityEntry>>,
     file_modified: Option<SystemTime>,
+    // Bytes of the log already parsed into entries, so appends can be read incrementally
+    read_offset: u64,
+    // Lines in the parsed log that weren't valid activity JSON; reset when the log is reparsed
+    parse_failures: usize,
+    // Whether the frontend has been told about parse failures this run
+    parse_failures_reported: bool,
+}
+
+// Cache for system idle time (querying the OS is relatively slow)
+struct SystemIdleCache {
+    idle_ms: i64,
+    last_checked: i64,
+}
+
+// Number of read-only connections kept open alongside the write connection
+const READ_POOL_SIZE: usize = 4;
+
+// Read-only connections so queries don't queue behind get_status or writes.
+// WAL mode lets these read while the write connection is busy.
+struct ReadPool {
+    conns: Vec<Mutex<Connection>>,
+    next: AtomicUsize,
+}
+
+impl ReadPool {
+    fn open(db_path: &Path, size: usize) -> rusqlite::Result<Self> {
+        let mut conns = Vec::with_capacity(size);
+        for _ in 0..size {
+            let conn = Connection::open_with_flags(
+                db_path,
+                OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+            )?;
+            conn.busy_timeout(Duration::from_secs(5))?;
+            conns.push(Mutex::new(conn));
+        }
+        Ok(ReadPool { conns, next: AtomicUsize::new(0) })
+    }
+
+    // Take the first idle connection, or wait on one in round-robin order if all are busy
+    fn get(&self) -> MutexGuard<'_, Connection> {
+        for conn in &self.conns {
+            if let Ok(guard) = conn.try_lock() {
+                return guard;
+            }
+        }
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.conns.len();
+        lock_recover(&self.conns[index], "read connection")
+    }
+}
+
+// Lock a mutex, recovering the guard if a thread panicked while holding it.
+// Without this one panic poisons the lock and every later command fails until restart.
+// An open transaction is rolled back when its guard unwinds, so the connection is still usable.
+fn lock_recover<'a, T>(mutex: &'a Mutex<T>, name: &str) -> MutexGuard<'a, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        eprintln!("Warning: {} lock was poisoned by a panic; recovering", name);
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
 }
 
-// Database connection wrapped in Mutex for thread safety
+// Single write connection wrapped in Mutex so writes serialize; reads go through the pool
 struct AppState {
     db: Mutex<Connection>,
+    readers: ReadPool,
     cache: Mutex<ActivityCache>,
+    idle_cache: Mutex<SystemIdleCache>,
 }
 
 // Data types matching the TypeScript interfaces
@@ -35,6 +163,11 @@ pub struct Project {
     pub color: String,
     pub hourly_rate: Option<f64>,
     pub created_at: i64,
+    pub archived: bool,
+    pub match_parents: bool,
+    // Target tracked time per week (from Monday) and per day; None means no goal
+    pub weekly_goal_ms: Option<i64>,
+    pub daily_goal_ms: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,6 +176,38 @@ pub struct BusinessInfo {
     pub name: String,
     pub email: Option<String>,
     pub tax_rate: f64,
+    pub currency: String,
+    pub idle_threshold_ms: i64,
+    // "dateRange" uses the billed period as the invoice number, "sequential" uses prefix + invoiceCounter
+    pub invoice_numbering: String,
+    pub invoice_prefix: String,
+    // PNG or JPEG drawn in the invoice header
+    pub logo_path: Option<String>,
+    // TrueType font for names and descriptions; the builtin Helvetica only covers Latin-1
+    pub font_path: Option<String>,
+    // Footer text on invoices; the due date is the invoice date plus payment_due_days (0 omits it)
+    pub notes: Option<String>,
+    pub payment_terms: Option<String>,
+    pub payment_due_days: i64,
+    // Hours between automatic database backups; 0 disables them
+    pub backup_interval_hours: i64,
+    // IANA name (e.g. "America/New_York") used for day/week boundaries and invoice dates; None uses the system zone
+    pub timezone: Option<String>,
+    // Pause auto-tracked sessions while the system has been idle past idle_threshold_ms
+    pub pause_auto_on_idle: bool,
+    // Treat invoice amounts as gross prices that already include the taxes, instead of adding them on top
+    pub tax_inclusive: bool,
+    // Named taxes shown as separate invoice lines (e.g. GST and PST); empty falls back to one "Tax" at tax_rate
+    pub taxes: Vec<TaxSetting>,
+    // Invoice paper size: "A4" or "Letter"
+    pub page_size: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaxSetting {
+    pub name: String,
+    pub rate: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +219,9 @@ pub struct TimeEntry {
     pub end_time: Option<i64>,
     pub claude_code_active: bool,
     pub description: Option<String>,
+    pub tags: Vec<String>,
+    // Set once the entry has been billed, to the project's invoices row
+    pub invoice_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,6 +232,43 @@ pub struct ActiveSession {
     pub claude_code_detected: bool,
     pub last_claude_check: i64,
     pub manual_mode: bool,
+    pub paused_ms: i64,
+    pub paused_at: Option<i64>,
+    // Paused by idle detection rather than the user, so it resumes when input returns
+    pub idle_paused: bool,
+    // Written to the entry when the session ends, however it ends
+    pub pending_description: Option<String>,
+}
+
+impl ActiveSession {
+    // Tracked time so far: time banked before the last pause plus the current running stretch.
+    // If the clock jumped back past start_time the running stretch counts as 0, not negative.
+    fn elapsed_ms(&self, now: i64) -> i64 {
+        match self.paused_at {
+            Some(_) => self.paused_ms.max(0),
+            None => (self.paused_ms + (now - self.start_time).max(0)).max(0),
+        }
+    }
+
+    // The part of elapsed_ms that falls after `since`, such as what a session begun yesterday
+    // adds to today
+    fn elapsed_since_ms(&self, since: i64, now: i64) -> i64 {
+        let end = self.paused_at.unwrap_or(now);
+        self.elapsed_ms(end).min((end - since).max(0))
+    }
+}
+
+// A project with its lifetime activity, cheap enough for pickers that don't need live status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectWithStats {
+    #[serde(flatten)]
+    pub project: Project,
+    pub total_ms: i64,
+    pub entry_count: i32,
+    // Start of the earliest and end of the latest completed entry; None with no entries
+    pub first_entry_at: Option<i64>,
+    pub last_entry_at: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,13 +277,52 @@ pub struct ProjectStatus {
     #[serde(flatten)]
     pub project: Project,
     pub is_tracking: bool,
+    pub is_paused: bool,
     pub manual_mode: bool,
     pub elapsed_time: i64,
     pub today_time: i64,
     pub week_time: i64,
     pub total_time: i64,
+    // Part of total_time from Claude-assisted entries; the rest was manual work
+    pub claude_time: i64,
     pub claude_state: String,
     pub claude_session_count: i32,
+    pub claude_sessions: Vec<ClaudeSession>,
+    // Share of the weekly goal reached this week (the daily goal today when there's no weekly one),
+    // including the running session; above 1.0 once the goal is passed. None without a goal.
+    pub goal_progress: Option<f64>,
+}
+
+// Where a project stands against its goals, including the running session. Remaining times
+// stop at 0 once a goal is met; every goal field is None when that goal isn't set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoalStatus {
+    pub project_id: String,
+    pub project_name: String,
+    pub today_ms: i64,
+    pub week_ms: i64,
+    pub daily_goal_ms: Option<i64>,
+    pub daily_remaining_ms: Option<i64>,
+    pub weekly_goal_ms: Option<i64>,
+    pub weekly_remaining_ms: Option<i64>,
+    pub goal_progress: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaudeSession {
+    pub session_id: String,
+    pub state: String,
+    pub last_activity: i64,
+}
+
+// How many logged hook events named each tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolUsage {
+    pub tool: String,
+    pub count: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,6 +331,12 @@ pub struct Status {
     pub projects: Vec<ProjectStatus>,
     pub today_total: i64,
     pub claude_total: i64,
+    // Today's and this week's time, running sessions included, priced at each project's
+    // hourly rate; projects without a rate add nothing
+    pub today_earnings: f64,
+    pub week_earnings: f64,
+    // IDLE_TIME_UNKNOWN (-1) when the OS idle time couldn't be read
+    pub system_idle_time: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -110,6 +360,27 @@ pub struct WeeklySummary {
     pub total_earnings: f64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagTotal {
+    pub tag: String,
+    pub total_ms: i64,
+    pub entry_count: i32,
+}
+
+// What generate_invoice rendered, so the UI can show it without re-querying
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceResult {
+    pub invoice_number: String,
+    pub file_path: String,
+    pub subtotal: f64,
+    pub tax_amount: f64,
+    pub total: f64,
+    pub total_hours: f64,
+    pub entry_count: i32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct InvoiceRecord {
@@ -123,30 +394,297 @@ pub struct InvoiceRecord {
     pub created_at: i64,
 }
 
+// An entry on an issued invoice. Entries deleted since were still billed, so they're listed
+// too, with when they were deleted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BilledEntry {
+    #[serde(flatten)]
+    pub entry: TimeEntry,
+    pub deleted_at: Option<i64>,
+}
+
+// One page of a project's entries, newest first, with the number of entries across all pages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryPage {
+    pub entries: Vec<TimeEntry>,
+    pub total_count: i64,
+}
+
+// Completed time not yet on any invoice, valued at the rate in effect when it was worked
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnbilledTotal {
+    pub project_id: String,
+    pub total_ms: i64,
+    pub total_hours: f64,
+    pub entry_count: i32,
+    // None when some of the time has no hourly rate to bill at
+    pub amount: Option<f64>,
+}
+
+// Two live entries in the same project whose spans intersect
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryOverlap {
+    pub project_id: String,
+    pub first_entry_id: String,
+    pub second_entry_id: String,
+}
+
+// Problems found by verify_integrity, by offending id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    // Entries ending before they start
+    pub inverted_entries: Vec<String>,
+    pub overlapping_entries: Vec<EntryOverlap>,
+    // Entries whose project no longer exists
+    pub orphaned_entries: Vec<String>,
+    // Project ids of active sessions whose project no longer exists
+    pub orphaned_sessions: Vec<String>,
+}
+
+// A CSV row import_entries_csv left out, by the line it starts on (the header is line 1)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedRow {
+    pub line: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub imported: u32,
+    pub skipped: Vec<SkippedRow>,
+}
+
+// Diagnostics for bug reports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppInfo {
+    pub version: String,
+    pub data_dir: String,
+    // user_version of the open database and the version this build migrates to
+    pub schema_version: i64,
+    pub latest_schema_version: i64,
+    pub hooks_installed: bool,
+    pub activity_log_lines: usize,
+    // Log lines rejected as invalid JSON, which means activity is being lost
+    pub activity_log_parse_failures: usize,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct ActivityEntry {
     event: String,
     session_id: String,
     cwd: Option<String>,
+    // Claude tool behind the event (e.g. "Edit", "Bash"); the hook writes "none" when there was none
+    tool: Option<String>,
     timestamp: i64,
 }
 
-// Get the data directory path
-fn get_data_dir() -> PathBuf {
-    let home = dirs::home_dir().expect("Could not find home directory");
-    home.join(".protimer")
+impl ActivityEntry {
+    // The hook can fire several times in one millisecond during a turn (prompt plus tool
+    // events); such repeats carry no new session state. Different tools are kept apart so
+    // the tool breakdown counts each one.
+    fn is_repeat_of(&self, other: &ActivityEntry) -> bool {
+        self.timestamp == other.timestamp
+            && self.event == other.event
+            && self.session_id == other.session_id
+            && self.cwd == other.cwd
+            && self.tool == other.tool
+    }
+}
+
+// Environment variable that relocates all ProTimer data (database, activity log, invoices)
+const DATA_DIR_ENV: &str = "PROTIMER_DATA_DIR";
+
+// Get the data directory path, creating it if needed: $PROTIMER_DATA_DIR when set, else ~/.protimer.
+// Errors instead of panicking when there is no home directory (e.g. some sandboxes) so commands can report it.
+fn get_data_dir() -> Result<PathBuf, ProtimerError> {
+    let dir = match std::env::var_os(DATA_DIR_ENV).filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => dirs::home_dir().ok_or(ProtimerError::NotFound("Could not find home directory".to_string()))?.join(".protimer"),
+    };
+
+    if !dir.exists() {
+        fs::create_dir_all(&dir).map_err(|e| ProtimerError::Io(format!("Failed to create data directory {}: {}", dir.display(), e)))?;
+    }
+
+    Ok(dir)
 }
 
-fn get_db_path() -> PathBuf {
-    get_data_dir().join("data.db")
+fn get_db_path() -> Result<PathBuf, ProtimerError> {
+    Ok(get_data_dir()?.join("data.db"))
 }
 
-fn get_activity_log_path() -> PathBuf {
-    get_data_dir().join("claude-activity.jsonl")
+fn get_activity_log_path() -> Result<PathBuf, ProtimerError> {
+    Ok(get_data_dir()?.join("claude-activity.jsonl"))
 }
 
+// Minimum gap between activity-log-changed events sent to the frontend
+const ACTIVITY_EVENT_DEBOUNCE: Duration = Duration::from_millis(500);
+
+// How often a status-updated event is pushed when the activity log is quiet. Status
+// computation also runs auto-stop and idle detection, so this keeps those moving.
+const STATUS_PUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+// Schema version reached by the baseline below. Databases created before versioning
+// report user_version 0 and are brought up to it idempotently.
+const BASELINE_SCHEMA_VERSION: i64 = 1;
+
+// Numbered migrations applied after the baseline, each exactly once and in order:
+// MIGRATIONS[i] upgrades the schema to BASELINE_SCHEMA_VERSION + i + 1.
+// Append only; never edit or reorder a migration that has shipped.
+const MIGRATIONS: &[(&str, &str)] = &[
+    (
+        "add business_info.backupIntervalHours",
+        "ALTER TABLE business_info ADD COLUMN backupIntervalHours INTEGER NOT NULL DEFAULT 24",
+    ),
+    ("add business_info.timezone", "ALTER TABLE business_info ADD COLUMN timezone TEXT"),
+    (
+        "pause auto sessions while idle",
+        "ALTER TABLE business_info ADD COLUMN pauseAutoOnIdle INTEGER NOT NULL DEFAULT 0;
+         ALTER TABLE active_sessions ADD COLUMN idlePaused INTEGER NOT NULL DEFAULT 0;",
+    ),
+    (
+        "add project_rates",
+        // Existing rates were applied to all past work, so they take effect from the epoch
+        "CREATE TABLE project_rates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            projectId TEXT NOT NULL,
+            hourlyRate REAL,
+            effectiveFrom INTEGER NOT NULL,
+            FOREIGN KEY (projectId) REFERENCES projects(id)
+         );
+         CREATE INDEX idx_project_rates_project ON project_rates(projectId, effectiveFrom);
+         INSERT INTO project_rates (projectId, hourlyRate, effectiveFrom)
+            SELECT id, hourlyRate, 0 FROM projects WHERE hourlyRate IS NOT NULL;",
+    ),
+    (
+        "add business_info.taxInclusive",
+        "ALTER TABLE business_info ADD COLUMN taxInclusive INTEGER NOT NULL DEFAULT 0",
+    ),
+    // JSON array of { name, rate }; NULL keeps using the single taxRate
+    ("add business_info.taxes", "ALTER TABLE business_info ADD COLUMN taxes TEXT"),
+    (
+        "add business_info.pageSize",
+        "ALTER TABLE business_info ADD COLUMN pageSize TEXT NOT NULL DEFAULT 'A4'",
+    ),
+    (
+        "add invoice_entries",
+        // entryId has no foreign key: billed entries may later be split, merged or purged,
+        // and the link is kept as a record of what was billed
+        "CREATE TABLE invoice_entries (
+            invoiceId TEXT NOT NULL,
+            entryId TEXT NOT NULL,
+            PRIMARY KEY (invoiceId, entryId),
+            FOREIGN KEY (invoiceId) REFERENCES invoices(id)
+         );
+         CREATE INDEX idx_invoice_entries_entry ON invoice_entries(entryId);",
+    ),
+    (
+        "add time_entries.invoiceId",
+        // Entries already linked to an invoice count as billed by the most recent one
+        "ALTER TABLE time_entries ADD COLUMN invoiceId TEXT;
+         UPDATE time_entries SET invoiceId = (
+            SELECT ie.invoiceId FROM invoice_entries ie JOIN invoices i ON i.id = ie.invoiceId
+            WHERE ie.entryId = time_entries.id
+            ORDER BY i.createdAt DESC LIMIT 1
+         );",
+    ),
+    (
+        "add active_sessions.pendingDescription",
+        "ALTER TABLE active_sessions ADD COLUMN pendingDescription TEXT",
+    ),
+    (
+        "add invoices.options",
+        // JSON InvoiceOptions; NULL for invoices issued before it was recorded
+        "ALTER TABLE invoices ADD COLUMN options TEXT",
+    ),
+    (
+        "add projects.invoiceHeaderNote",
+        "ALTER TABLE projects ADD COLUMN invoiceHeaderNote TEXT",
+    ),
+    (
+        "add projects goals",
+        "ALTER TABLE projects ADD COLUMN weeklyGoalMs INTEGER;
+         ALTER TABLE projects ADD COLUMN dailyGoalMs INTEGER;",
+    ),
+    (
+        "add settings",
+        // App behaviour settings move out of business_info; the old columns are no longer read
+        "CREATE TABLE settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+         );
+         INSERT INTO settings (key, value)
+            SELECT 'idleThresholdMs', CAST(idleThresholdMs AS TEXT) FROM business_info WHERE id = 1;
+         INSERT INTO settings (key, value)
+            SELECT 'backupIntervalHours', CAST(backupIntervalHours AS TEXT) FROM business_info WHERE id = 1;
+         INSERT INTO settings (key, value)
+            SELECT 'timezone', timezone FROM business_info WHERE id = 1 AND timezone IS NOT NULL AND timezone != '';
+         INSERT INTO settings (key, value)
+            SELECT 'pauseAutoOnIdle', CASE WHEN pauseAutoOnIdle = 1 THEN 'true' ELSE 'false' END
+            FROM business_info WHERE id = 1;",
+    ),
+];
+
 // Initialize database
 fn init_db(conn: &Connection) -> rusqlite::Result<()> {
+    // SQLite ignores the FOREIGN KEY clauses below unless enforcement is enabled per connection
+    conn.execute_batch("PRAGMA foreign_keys = ON")?;
+
+    // WAL lets the read pool query while a write is in progress
+    conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get::<_, String>(0))?;
+
+    let mut version = schema_version(conn)?;
+
+    if version < BASELINE_SCHEMA_VERSION {
+        let tx = conn.unchecked_transaction()?;
+        create_baseline_schema(&tx)?;
+        tx.pragma_update(None, "user_version", BASELINE_SCHEMA_VERSION)?;
+        tx.commit()?;
+        version = BASELINE_SCHEMA_VERSION;
+    }
+
+    for (index, (description, sql)) in MIGRATIONS.iter().enumerate() {
+        let target = BASELINE_SCHEMA_VERSION + index as i64 + 1;
+        if target <= version {
+            continue;
+        }
+
+        // Each migration commits together with its version bump, so a failure is retried next launch
+        let tx = conn.unchecked_transaction()?;
+        if let Err(e) = tx.execute_batch(sql) {
+            eprintln!("Migration {} ({}) failed: {}", target, description, e);
+            return Err(e);
+        }
+        tx.pragma_update(None, "user_version", target)?;
+        tx.commit()?;
+        version = target;
+    }
+
+    Ok(())
+}
+
+fn schema_version(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+// Pre-versioning databases may already have any of the baseline columns
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> rusqlite::Result<()> {
+    if !get_table_columns(conn, table)?.iter().any(|c| c == column) {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+    Ok(())
+}
+
+// Schema as it stood when versioning was introduced. Safe to run against any older database.
+fn create_baseline_schema(conn: &Connection) -> rusqlite::Result<()> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS projects (
             id TEXT PRIMARY KEY,
@@ -184,16 +722,14 @@ fn init_db(conn: &Connection) -> rusqlite::Result<()> {
     )?;
 
     // Migration: add manualMode column if it doesn't exist
-    let _ = conn.execute(
-        "ALTER TABLE active_sessions ADD COLUMN manualMode INTEGER NOT NULL DEFAULT 0",
-        [],
-    );
+    add_column_if_missing(conn, "active_sessions", "manualMode", "INTEGER NOT NULL DEFAULT 0")?;
+
+    // Migration: add pause tracking columns to active_sessions
+    add_column_if_missing(conn, "active_sessions", "pausedMs", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "active_sessions", "pausedAt", "INTEGER")?;
 
     // Migration: add hourlyRate column to projects
-    let _ = conn.execute(
-        "ALTER TABLE projects ADD COLUMN hourlyRate REAL",
-        [],
-    );
+    add_column_if_missing(conn, "projects", "hourlyRate", "REAL")?;
 
     // Create business_info table
     conn.execute(
@@ -210,11 +746,11 @@ fn init_db(conn: &Connection) -> rusqlite::Result<()> {
     )?;
 
     // Insert default business info if not exists
-    let _ = conn.execute(
+    conn.execute(
         "INSERT OR IGNORE INTO business_info (id, name, address, email, phone, taxRate, invoiceCounter)
          VALUES (1, '', '', '', '', 0.0, 1)",
         [],
-    );
+    )?;
 
     // Create invoices table
     conn.execute(
@@ -232,48 +768,128 @@ fn init_db(conn: &Connection) -> rusqlite::Result<()> {
         [],
     )?;
 
+    // Migration: add currency to business_info
+    add_column_if_missing(conn, "business_info", "currency", "TEXT NOT NULL DEFAULT 'USD'")?;
+
+    // Migration: archived flag on projects
+    add_column_if_missing(conn, "projects", "archived", "INTEGER NOT NULL DEFAULT 0")?;
+
+    // Migration: idle auto-stop threshold for manual sessions (0 disables)
+    add_column_if_missing(conn, "business_info", "idleThresholdMs", "INTEGER NOT NULL DEFAULT 600000")?;
+
+    // Migration: invoice numbering mode and prefix for sequential numbers
+    add_column_if_missing(conn, "business_info", "invoiceNumbering", "TEXT NOT NULL DEFAULT 'dateRange'")?;
+    add_column_if_missing(conn, "business_info", "invoicePrefix", "TEXT NOT NULL DEFAULT 'INV-'")?;
+
+    // Migration: optional logo for the invoice header
+    add_column_if_missing(conn, "business_info", "logoPath", "TEXT")?;
+
+    // Migration: optional TrueType font for non-Latin invoice text
+    add_column_if_missing(conn, "business_info", "fontPath", "TEXT")?;
+
+    // Migration: invoice footer notes, payment terms and "net N days" due date
+    add_column_if_missing(conn, "business_info", "notes", "TEXT")?;
+    add_column_if_missing(conn, "business_info", "paymentTerms", "TEXT")?;
+    add_column_if_missing(conn, "business_info", "paymentDueDays", "INTEGER NOT NULL DEFAULT 30")?;
+
+    // Migration: opt-in matching of Claude sessions started in a parent directory
+    add_column_if_missing(conn, "projects", "matchParents", "INTEGER NOT NULL DEFAULT 0")?;
+
     // Migration: add client fields to projects
-    let _ = conn.execute(
-        "ALTER TABLE projects ADD COLUMN clientName TEXT",
-        [],
-    );
-    let _ = conn.execute(
-        "ALTER TABLE projects ADD COLUMN clientEmail TEXT",
-        [],
-    );
-    let _ = conn.execute(
-        "ALTER TABLE projects ADD COLUMN clientAddress TEXT",
+    add_column_if_missing(conn, "projects", "clientName", "TEXT")?;
+    add_column_if_missing(conn, "projects", "clientEmail", "TEXT")?;
+    add_column_if_missing(conn, "projects", "clientAddress", "TEXT")?;
+
+    // Migration: soft-delete marker for time entries
+    add_column_if_missing(conn, "time_entries", "deletedAt", "INTEGER")?;
+
+    // Migration: comma-separated tags for reporting across projects
+    add_column_if_missing(conn, "time_entries", "tags", "TEXT")?;
+
+    // Cleanup: drop inverted entries left by earlier manual edits; their negative
+    // durations corrupt every SUM(endTime - startTime) aggregate
+    conn.execute(
+        "DELETE FROM time_entries WHERE endTime IS NOT NULL AND endTime < startTime",
         [],
-    );
+    )?;
 
     // Performance indexes
-    let _ = conn.execute(
+    conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_time_entries_project_start ON time_entries(projectId, startTime)",
         [],
-    );
-    let _ = conn.execute(
+    )?;
+    conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_time_entries_claude ON time_entries(claudeCodeActive)",
         [],
-    );
+    )?;
 
     Ok(())
 }
 
+// Columns selected for active_sessions rows, in the order active_session_from_row expects
+const ACTIVE_SESSION_COLUMNS: &str =
+    "projectId, startTime, claudeCodeDetected, lastClaudeCheck, manualMode, pausedMs, pausedAt, idlePaused, pendingDescription";
+
+fn active_session_from_row(row: &rusqlite::Row) -> rusqlite::Result<ActiveSession> {
+    Ok(ActiveSession {
+        project_id: row.get(0)?,
+        start_time: row.get(1)?,
+        claude_code_detected: row.get::<_, i32>(2)? == 1,
+        last_claude_check: row.get(3)?,
+        manual_mode: row.get::<_, i32>(4)? == 1,
+        paused_ms: row.get(5)?,
+        paused_at: row.get(6)?,
+        idle_paused: row.get::<_, i32>(7)? == 1,
+        pending_description: row.get(8)?,
+    })
+}
+
 // Generate unique ID
 fn generate_id() -> String {
     uuid::Uuid::new_v4().to_string()
 }
 
-// Get current timestamp in milliseconds
+// Get current timestamp in milliseconds. A clock set before the epoch gives a negative
+// value instead of panicking.
 fn now_ms() -> i64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as i64
+    match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        Ok(since_epoch) => since_epoch.as_millis() as i64,
+        Err(e) => -(e.duration().as_millis() as i64),
+    }
+}
+
+lazy_static::lazy_static! {
+    // Resolved paths, since matching runs for every activity entry on every status poll
+    static ref CANONICAL_PATHS: Mutex<std::collections::HashMap<String, String>> =
+        Mutex::new(std::collections::HashMap::new());
+}
+
+// Resolve symlinks and `..` segments so differently spelled paths compare equal.
+// Falls back to the raw string when the path doesn't exist on disk.
+fn canonicalize_path(path: &str) -> String {
+    if let Ok(cache) = CANONICAL_PATHS.lock() {
+        if let Some(resolved) = cache.get(path) {
+            return resolved.clone();
+        }
+    }
+
+    match fs::canonicalize(path) {
+        Ok(resolved) => {
+            let resolved = resolved.to_string_lossy().to_string();
+            // Only cache hits; a missing path may be created later
+            if let Ok(mut cache) = CANONICAL_PATHS.lock() {
+                cache.insert(path.to_string(), resolved.clone());
+            }
+            resolved
+        }
+        Err(_) => path.to_string(),
+    }
 }
 
-// Check if cwd_path is within project_path (same or subfolder only)
-fn is_path_within_project(cwd_path: &str, project_path: &str) -> bool {
+// Check if cwd_path is within project_path (same or subfolder, or an ancestor when match_parents is set)
+fn is_path_within_project(cwd_path: &str, project_path: &str, match_parents: bool) -> bool {
+    let cwd_path = canonicalize_path(cwd_path);
+    let project_path = canonicalize_path(project_path);
     let cwd = cwd_path.trim_end_matches('/');
     let project = project_path.trim_end_matches('/');
 
@@ -285,17 +901,50 @@ fn is_path_within_project(cwd_path: &str, project_path: &str) -> bool {
     if cwd.starts_with(&format!("{}/", project)) {
         return true;
     }
-    // Don't match parent directories - too aggressive
+    // Parent directories only match when the project opts in (e.g. Claude run from a monorepo root)
+    if match_parents && !cwd.is_empty() && project.starts_with(&format!("{}/", cwd)) {
+        return true;
+    }
     false
 }
 
+// The single project a cwd belongs to when several match. A project containing the cwd beats
+// one matched only as a parent (match_parents), then the longest path wins, so nested projects
+// like /work and /work/app never both claim the same Claude session.
+fn owning_project<'p>(cwd: &str, projects: &'p [Project]) -> Option<&'p Project> {
+    projects
+        .iter()
+        .filter(|p| is_path_within_project(cwd, &p.path, p.match_parents))
+        .max_by_key(|p| {
+            let contains_cwd = is_path_within_project(cwd, &p.path, false);
+            (contains_cwd, canonicalize_path(&p.path).trim_end_matches('/').len())
+        })
+}
+
+// Owning project id for each distinct cwd in the activity index (None when no project matches)
+fn index_cwd_owners<'a>(
+    activity: &[SessionActivity<'a>],
+    projects: &[Project],
+) -> std::collections::HashMap<&'a str, Option<String>> {
+    let mut owners = std::collections::HashMap::new();
+    for record in activity {
+        owners
+            .entry(record.cwd)
+            .or_insert_with(|| owning_project(record.cwd, projects).map(|p| p.id.clone()));
+    }
+    owners
+}
+
 // Refresh activity log cache if file changed
 fn refresh_activity_cache(cache: &mut ActivityCache) {
-    let log_path = get_activity_log_path();
+    let log_path = match get_activity_log_path() {
+        Ok(path) => path,
+        Err(_) => return,
+    };
 
-    let current_modified = fs::metadata(&log_path)
-        .ok()
-        .and_then(|m| m.modified().ok());
+    let metadata = fs::metadata(&log_path).ok();
+    let current_modified = metadata.as_ref().and_then(|m| m.modified().ok());
+    let current_len = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
 
     let needs_refresh = match (&cache.file_modified, &current_modified) {
         (Some(cached), Some(current)) => cached != current,
@@ -304,126 +953,451 @@ fn refresh_activity_cache(cache: &mut ActivityCache) {
     };
 
     if needs_refresh {
-        let mut new_entries = Vec::new();
-        if let Ok(file) = fs::File::open(&log_path) {
-            let reader = BufReader::new(file);
-            for line in reader.lines().map_while(Result::ok) {
-                if let Ok(entry) = serde_json::from_str::<ActivityEntry>(&line) {
-                    new_entries.push(entry);
+        // The hook only appends, so a file at least as long as what we've read just grew.
+        // A shorter file was rotated and is reparsed from the start.
+        let start = if current_len >= cache.read_offset { cache.read_offset } else { 0 };
+        if start == 0 {
+            cache.entries = Arc::new(Vec::new());
+            cache.parse_failures = 0;
+        }
+
+        let mut bytes = Vec::new();
+        let read_ok = fs::File::open(&log_path)
+            .and_then(|mut file| {
+                file.seek(SeekFrom::Start(start))?;
+                file.read_to_end(&mut bytes)
+            })
+            .is_ok();
+
+        // Only consume complete lines; a partially written last line is picked up on the next change
+        let consumed = if read_ok {
+            bytes.iter().rposition(|b| *b == b'\n').map(|i| i + 1).unwrap_or(0)
+        } else {
+            0
+        };
+
+        // make_mut only copies if a get_status call still holds the previous snapshot
+        let entries = Arc::make_mut(&mut cache.entries);
+        let mut failures = 0;
+        for line in bytes[..consumed].split(|b| *b == b'\n') {
+            if line.iter().all(|b| b.is_ascii_whitespace()) {
+                continue;
+            }
+            match serde_json::from_slice::<ActivityEntry>(line) {
+                Ok(entry) => {
+                    // Collapse consecutive repeats, e.g. from logs written before the hook deduplicated
+                    if entries.last().is_some_and(|last| entry.is_repeat_of(last)) {
+                        continue;
+                    }
+                    entries.push(entry);
+                }
+                Err(e) => {
+                    // Rejected activity would otherwise vanish without a trace
+                    if failures == 0 {
+                        let text = String::from_utf8_lossy(line);
+                        eprintln!("Skipping unparseable activity log line ({}): {}", e, text.chars().take(200).collect::<String>());
+                    }
+                    failures += 1;
                 }
             }
         }
-        cache.entries = Arc::new(new_entries);
+        if failures > 1 {
+            eprintln!("Skipped {} unparseable activity log lines", failures);
+        }
+        cache.parse_failures += failures;
+
+        cache.read_offset = start + consumed as u64;
         cache.file_modified = current_modified;
     }
 }
 
 
-// Get Claude sessions for a project from cached activity log
+// Latest activity-log event for one session in one working directory
+struct SessionActivity<'a> {
+    session_id: &'a str,
+    cwd: &'a str,
+    active: bool,
+    timestamp: i64,
+    // Position in the log, so the last matching event wins as it would in a full scan
+    seq: usize,
+}
+
+// Collapse the log to one record per (session, cwd). Built once per get_status call so each
+// project scans distinct sessions instead of every log entry.
+fn index_activity_entries(entries: &[ActivityEntry]) -> Vec<SessionActivity<'_>> {
+    let mut positions: std::collections::HashMap<(&str, &str), usize> = std::collections::HashMap::new();
+    let mut index: Vec<SessionActivity> = Vec::new();
+
+    for (seq, entry) in entries.iter().enumerate() {
+        let cwd = match &entry.cwd {
+            Some(cwd) => cwd.as_str(),
+            None => continue,
+        };
+        let record = SessionActivity {
+            session_id: &entry.session_id,
+            cwd,
+            active: entry.event == "UserPromptSubmit" || entry.event == "PostToolUse",
+            timestamp: entry.timestamp,
+            seq,
+        };
+        match positions.get(&(record.session_id, cwd)).copied() {
+            Some(i) => index[i] = record,
+            None => {
+                positions.insert((record.session_id, cwd), index.len());
+                index.push(record);
+            }
+        }
+    }
+
+    index
+}
+
+// Get Claude sessions for a project from the indexed activity log
 // Hooks are source of truth for starting, process detection is fallback for stopping
 fn get_claude_sessions_for_project_cached(
-    project_path: &str,
-    entries: &[ActivityEntry],
-) -> Vec<(String, String, i64)> {
+    project_id: &str,
+    cwd_owners: &std::collections::HashMap<&str, Option<String>>,
+    activity: &[SessionActivity],
+) -> Vec<ClaudeSession> {
     let now = now_ms();
     // Sessions older than 10 minutes with no Stop are considered stale
     let stale_threshold = 10 * 60 * 1000; // 10 minutes in ms
 
-    let mut sessions: std::collections::HashMap<String, (String, i64)> = std::collections::HashMap::new();
+    // session_id -> (state, timestamp, seq) of the latest event inside this project
+    let mut sessions: std::collections::HashMap<String, (String, i64, usize)> = std::collections::HashMap::new();
 
-    for entry in entries {
-        if let Some(cwd) = &entry.cwd {
-            if is_path_within_project(cwd, project_path) {
-                let state = if entry.event == "UserPromptSubmit" {
-                    "active"
-                } else {
-                    "stopped"
-                };
-                sessions.insert(entry.session_id.clone(), (state.to_string(), entry.timestamp));
-            }
+    for record in activity {
+        if cwd_owners.get(record.cwd).and_then(|owner| owner.as_deref()) != Some(project_id) {
+            continue;
+        }
+        let is_newer = !sessions
+            .get(record.session_id)
+            .is_some_and(|(_, _, seq)| record.seq < *seq);
+        if is_newer {
+            let state = if record.active { "active" } else { "stopped" };
+            sessions.insert(record.session_id.to_string(), (state.to_string(), record.timestamp, record.seq));
         }
     }
 
     // Filter out stale "active" sessions - if last activity was > 10 min ago, treat as stopped
-    sessions
+    let mut sessions: Vec<ClaudeSession> = sessions
         .into_iter()
-        .map(|(id, (state, ts))| {
-            if state == "active" && (now - ts) > stale_threshold {
-                (id, "stopped".to_string(), ts)
+        .map(|(id, (state, ts, _))| ClaudeSession {
+            session_id: id,
+            state: if state == "active" && (now - ts) > stale_threshold {
+                "stopped".to_string()
             } else {
-                (id, state, ts)
-            }
+                state
+            },
+            last_activity: ts,
         })
-        .collect()
+        .collect();
+
+    // Most recently active first
+    sessions.sort_by(|a, b| b.last_activity.cmp(&a.last_activity));
+    sessions
 }
 
 
-// Get start of today in milliseconds
-fn get_today_start_ms() -> i64 {
-    let now = chrono::Local::now();
-    let today = now.date_naive().and_hms_opt(0, 0, 0).unwrap();
-    today.and_local_timezone(chrono::Local).unwrap().timestamp_millis()
+lazy_static::lazy_static! {
+    // The timezone setting parsed once; None means the system zone (chrono::Local)
+    static ref TIMEZONE_OVERRIDE: Mutex<Option<chrono_tz::Tz>> = Mutex::new(None);
 }
 
-// Get start of week (Monday) in milliseconds
-fn get_week_start_ms() -> i64 {
-    use chrono::{Datelike, Duration, Local};
-    let now = Local::now();
-    let days_since_monday = now.weekday().num_days_from_monday() as i64;
-    let monday = now.date_naive() - Duration::days(days_since_monday);
-    monday.and_hms_opt(0, 0, 0).unwrap()
-        .and_local_timezone(Local).unwrap()
-        .timestamp_millis()
+// Reload the timezone setting after anything that may have changed it
+fn refresh_timezone_override(conn: &Connection) {
+    let timezone = load_setting(conn, SETTING_TIMEZONE)
+        .ok()
+        .flatten()
+        .and_then(|name| name.parse::<chrono_tz::Tz>().ok());
+    *lock_recover(&TIMEZONE_OVERRIDE, "timezone") = timezone;
 }
 
-// ============== HOOK MANAGEMENT ==============
+fn timezone_override() -> Option<chrono_tz::Tz> {
+    *lock_recover(&TIMEZONE_OVERRIDE, "timezone")
+}
 
-fn get_hooks_dir() -> PathBuf {
-    get_data_dir().join("hooks")
+// Current wall-clock time in the configured zone
+fn local_now() -> chrono::DateTime<chrono::FixedOffset> {
+    match timezone_override() {
+        Some(tz) => chrono::Utc::now().with_timezone(&tz).fixed_offset(),
+        None => chrono::Local::now().fixed_offset(),
+    }
 }
 
-fn get_hook_script_path() -> PathBuf {
-    get_hooks_dir().join("track-activity.sh")
+// A millisecond timestamp as wall-clock time in the configured zone
+fn to_local(ms: i64) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+    let utc = chrono::DateTime::from_timestamp_millis(ms)?;
+    Some(match timezone_override() {
+        Some(tz) => utc.with_timezone(&tz).fixed_offset(),
+        None => utc.with_timezone(&chrono::Local).fixed_offset(),
+    })
 }
 
-fn get_claude_settings_path() -> PathBuf {
-    dirs::home_dir()
-        .expect("Could not find home directory")
-        .join(".claude")
-        .join("settings.json")
+// Resolve a wall-clock time in the configured zone; ambiguous or skipped around DST changes
+fn local_datetime(naive: chrono::NaiveDateTime) -> chrono::LocalResult<chrono::DateTime<chrono::FixedOffset>> {
+    match timezone_override() {
+        Some(tz) => naive.and_local_timezone(tz).map(|dt| dt.fixed_offset()),
+        None => naive.and_local_timezone(chrono::Local).map(|dt| dt.fixed_offset()),
+    }
 }
 
-const HOOK_SCRIPT: &str = r#"#!/bin/bash
-# Claude Code Activity Hook for ProTimer
-# This script is called by Claude Code hooks to track when Claude is actively working
+// Get start of today in milliseconds
+fn get_today_start_ms() -> i64 {
+    local_day_start_ms(local_now().date_naive())
+}
+
+// Get start of week (Monday) in milliseconds
+fn get_week_start_ms() -> i64 {
+    use chrono::{Datelike, Duration};
+    let now = local_now();
+    let days_since_monday = now.weekday().num_days_from_monday() as i64;
+    local_day_start_ms(now.date_naive() - Duration::days(days_since_monday))
+}
+
+// ============== SYSTEM IDLE DETECTION ==============
+
+// How long a cached idle reading stays valid
+const SYSTEM_IDLE_CACHE_MS: i64 = 5000;
+
+// Idle time after which a manual session is auto-stopped, unless configured otherwise
+const DEFAULT_IDLE_THRESHOLD_MS: i64 = 10 * 60 * 1000;
+
+// Returned when the OS query fails or its output can't be parsed, so it isn't mistaken for
+// input just now. Idle-based features do nothing while idle time is unknown.
+const IDLE_TIME_UNKNOWN: i64 = -1;
+
+// Milliseconds since the last keyboard/mouse input, via IOKit's HIDIdleTime (nanoseconds)
+#[cfg(target_os = "macos")]
+fn do_get_system_idle_time() -> i64 {
+    let output = match Command::new("ioreg").args(["-c", "IOHIDSystem"]).output() {
+        Ok(o) if o.status.success() => o,
+        _ => return IDLE_TIME_UNKNOWN,
+    };
+
+    // Take the first HIDIdleTime value that parses, since the output format varies between versions
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .filter(|line| line.contains("HIDIdleTime"))
+        .filter_map(|line| line.split('=').nth(1))
+        .find_map(|value| value.trim().parse::<i64>().ok())
+        .map(|ns| ns / 1_000_000)
+        .unwrap_or(IDLE_TIME_UNKNOWN)
+}
+
+// Milliseconds since the last input, via the session D-Bus. KDE and most other desktops
+// implement org.freedesktop.ScreenSaver; GNOME only exposes Mutter's IdleMonitor.
+#[cfg(target_os = "linux")]
+fn do_get_system_idle_time() -> i64 {
+    let queries: [(&str, &str, &str); 2] = [
+        (
+            "org.freedesktop.ScreenSaver",
+            "/org/freedesktop/ScreenSaver",
+            "org.freedesktop.ScreenSaver.GetSessionIdleTime",
+        ),
+        (
+            "org.gnome.Mutter.IdleMonitor",
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            "org.gnome.Mutter.IdleMonitor.GetIdletime",
+        ),
+    ];
+
+    for (dest, object_path, method) in queries {
+        let dest_arg = format!("--dest={}", dest);
+        let output = match Command::new("dbus-send")
+            .args(["--session", "--print-reply", dest_arg.as_str(), object_path, method])
+            .output()
+        {
+            Ok(o) if o.status.success() => o,
+            _ => continue,
+        };
+
+        // Reply looks like: "method return ...\n   uint32 12345"
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let idle_ms = stdout
+            .lines()
+            .map(str::trim)
+            .find(|line| line.starts_with("uint32") || line.starts_with("uint64"))
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|value| value.parse::<i64>().ok());
+
+        if let Some(ms) = idle_ms {
+            return ms;
+        }
+    }
+
+    IDLE_TIME_UNKNOWN
+}
+
+// Milliseconds since the last input, via GetLastInputInfo. Both values are 32-bit tick
+// counts, so wrapping_sub keeps the result correct across the ~49.7 day rollover.
+#[cfg(target_os = "windows")]
+fn do_get_system_idle_time() -> i64 {
+    use windows_sys::Win32::System::SystemInformation::GetTickCount;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+
+    // SAFETY: info is a correctly sized LASTINPUTINFO that outlives the call
+    if unsafe { GetLastInputInfo(&mut info) } == 0 {
+        return IDLE_TIME_UNKNOWN;
+    }
+
+    let now = unsafe { GetTickCount() };
+    now.wrapping_sub(info.dwTime) as i64
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+fn do_get_system_idle_time() -> i64 {
+    IDLE_TIME_UNKNOWN
+}
+
+// Refresh idle time at most once every 5 seconds
+fn refresh_system_idle_cache(cache: &mut SystemIdleCache) -> i64 {
+    let now = now_ms();
+    if now - cache.last_checked >= SYSTEM_IDLE_CACHE_MS {
+        cache.idle_ms = do_get_system_idle_time();
+        cache.last_checked = now;
+    }
+    cache.idle_ms
+}
+
+// ============== HOOK MANAGEMENT ==============
+
+fn get_hooks_dir() -> Result<PathBuf, ProtimerError> {
+    Ok(get_data_dir()?.join("hooks"))
+}
+
+fn get_claude_settings_path() -> Result<PathBuf, ProtimerError> {
+    let home = dirs::home_dir().ok_or(ProtimerError::NotFound("Could not find home directory".to_string()))?;
+    Ok(home.join(".claude").join("settings.json"))
+}
+
+// Argument to `protimer hook` naming a relocated data dir
+const HOOK_DATA_DIR_ARG: &str = "--data-dir";
+
+// This binary with the `hook` subcommand, quoted so app bundle paths with spaces survive the shell
+fn get_hook_exe_command() -> Result<String, ProtimerError> {
+    let exe = std::env::current_exe()
+        .map_err(|e| ProtimerError::Io(format!("Failed to locate ProTimer executable: {}", e)))?;
+    Ok(format!("\"{}\" hook", exe.to_string_lossy()))
+}
+
+// Command registered with Claude Code. Claude doesn't inherit our environment, so a relocated
+// data dir is passed as an argument, which every shell reads the same way (unlike a VAR=value prefix).
+fn get_hook_command() -> Result<String, ProtimerError> {
+    let command = get_hook_exe_command()?;
+    match std::env::var_os(DATA_DIR_ENV).filter(|v| !v.is_empty()) {
+        Some(dir) => {
+            let dir = PathBuf::from(dir).to_string_lossy().to_string();
+            // A trailing backslash would escape the closing quote on Windows
+            let trimmed = dir.trim_end_matches(['\\', '/']);
+            let dir = if trimmed.is_empty() { dir.as_str() } else { trimmed };
+            Ok(format!("{} {} \"{}\"", command, HOOK_DATA_DIR_ARG, dir))
+        }
+        None => Ok(command),
+    }
+}
+
+// Whether a registered command runs this binary's hook, whichever data dir it passes. Also
+// matches the older PROTIMER_DATA_DIR="..." prefix form, so reinstalling replaces it.
+fn is_own_hook_command(command: &str, exe_command: &str) -> bool {
+    command.find(exe_command).is_some_and(|i| {
+        let rest = &command[i + exe_command.len()..];
+        rest.is_empty() || rest.starts_with(' ')
+    })
+}
+
+// Hook payload sent by Claude Code on stdin (only the fields we log)
+#[derive(Debug, Default, Deserialize)]
+struct HookPayload {
+    hook_event_name: Option<String>,
+    session_id: Option<String>,
+    tool_name: Option<String>,
+    cwd: Option<String>,
+}
+
+// Entry point for `protimer hook`. Appends one activity line per Claude Code hook event.
+// Errors are swallowed so a logging failure never interrupts Claude.
+pub fn run_hook() {
+    // `hook --data-dir <dir>` points this process at a relocated data dir. The hook is
+    // single-threaded and sets it before anything reads it.
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(dir) = args.iter().position(|a| a == HOOK_DATA_DIR_ARG).and_then(|i| args.get(i + 1)) {
+        std::env::set_var(DATA_DIR_ENV, dir);
+    }
+
+    let mut input = String::new();
+    let _ = std::io::stdin().read_to_string(&mut input);
+    let payload: HookPayload = serde_json::from_str(&input).unwrap_or_default();
+
+    let entry = ActivityEntry {
+        event: payload.hook_event_name.unwrap_or_else(|| "unknown".to_string()),
+        session_id: payload.session_id.unwrap_or_else(|| "unknown".to_string()),
+        cwd: Some(payload.cwd.unwrap_or_else(|| "unknown".to_string())),
+        tool: Some(payload.tool_name.unwrap_or_else(|| "none".to_string())),
+        timestamp: now_ms(),
+    };
+
+    // Skip exact repeats of the previous line to keep the log from growing on rapid-fire events
+    let is_repeat = get_activity_log_path()
+        .ok()
+        .and_then(|path| read_last_activity_entry(&path))
+        .is_some_and(|last| entry.is_repeat_of(&last));
+    if is_repeat {
+        return;
+    }
 
-# Activity log location - shared across all projects
-ACTIVITY_DIR="$HOME/.protimer"
-ACTIVITY_LOG="$ACTIVITY_DIR/claude-activity.jsonl"
+    // serde_json handles escaping, so cwds with quotes or backslashes stay parseable
+    let line = serde_json::json!({
+        "event": entry.event,
+        "session_id": entry.session_id,
+        "tool": entry.tool,
+        "cwd": entry.cwd,
+        "timestamp": entry.timestamp,
+    });
 
-# Ensure directory exists
-mkdir -p "$ACTIVITY_DIR"
+    let _ = append_activity_line(&line.to_string());
+}
 
-# Read hook input from stdin
-input=$(cat)
+// Last complete line of the activity log, read from the tail so the hook stays cheap
+fn read_last_activity_entry(log_path: &Path) -> Option<ActivityEntry> {
+    let mut file = fs::File::open(log_path).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(4096))).ok()?;
+
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).ok()?;
+    let trimmed = bytes.strip_suffix(b"\n")?;
+    let last_line = match trimmed.iter().rposition(|b| *b == b'\n') {
+        Some(i) => &trimmed[i + 1..],
+        None => trimmed,
+    };
+    serde_json::from_slice(last_line).ok()
+}
 
-# Parse event details
-event=$(echo "$input" | jq -r '.hook_event_name // "unknown"')
-session_id=$(echo "$input" | jq -r '.session_id // "unknown"')
-tool_name=$(echo "$input" | jq -r '.tool_name // "none"')
-cwd=$(echo "$input" | jq -r '.cwd // "unknown"')
-timestamp=$(($(date +%s) * 1000))  # Unix timestamp in milliseconds (macOS compatible)
+fn append_activity_line(line: &str) -> std::io::Result<()> {
+    let log_path = get_activity_log_path().map_err(|e| std::io::Error::new(std::io::ErrorKind::NotFound, e))?;
 
-# Log the activity
-echo "{\"event\":\"$event\",\"session_id\":\"$session_id\",\"tool\":\"$tool_name\",\"cwd\":\"$cwd\",\"timestamp\":$timestamp}" >> "$ACTIVITY_LOG"
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(&log_path)?;
+    writeln!(file, "{}", line)?;
+    drop(file);
 
-# Keep log file from growing too large (keep last 1000 lines)
-if [ $(wc -l < "$ACTIVITY_LOG") -gt 1000 ]; then
-  tail -500 "$ACTIVITY_LOG" > "$ACTIVITY_LOG.tmp" && mv "$ACTIVITY_LOG.tmp" "$ACTIVITY_LOG"
-fi
+    // Keep log file from growing too large (past 1000 lines, keep the last 500)
+    let content = fs::read_to_string(&log_path)?;
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.len() > 1000 {
+        let tmp_path = log_path.with_extension("jsonl.tmp");
+        fs::write(&tmp_path, lines[lines.len() - 500..].join("\n") + "\n")?;
+        fs::rename(&tmp_path, &log_path)?;
+    }
 
-exit 0
-"#;
+    Ok(())
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -431,61 +1405,109 @@ pub struct HooksStatus {
     pub script_installed: bool,
     pub settings_configured: bool,
     pub fully_installed: bool,
+    // Commands from other tools registered on the events we hook, so the UI can warn
+    pub foreign_hooks: Vec<String>,
 }
 
-fn check_hooks_status() -> HooksStatus {
-    let script_path = get_hook_script_path();
-    let settings_path = get_claude_settings_path();
+// Claude Code events ProTimer registers on, with the matcher for each
+const HOOK_EVENTS: [(&str, Option<&str>); 4] = [
+    ("UserPromptSubmit", None),
+    ("Stop", Some("*")),
+    // Carries tool_name for the tool breakdown; also marks the session active again after a permission prompt
+    ("PostToolUse", Some("*")),
+    // Pauses tracking while Claude waits for permission approval
+    ("Notification", Some("permission_prompt")),
+];
+
+// Hook command written by older versions, which ran a bash script
+fn is_legacy_hook_command(command: &str) -> bool {
+    command.ends_with("track-activity.sh")
+}
 
-    let script_installed = script_path.exists();
+// Whether any hook entry in the Claude settings runs this binary's hook
+fn settings_contain_hook_command(settings: &serde_json::Value, exe_command: &str) -> bool {
+    settings
+        .get("hooks")
+        .and_then(|h| h.as_object())
+        .map(|events| {
+            events
+                .values()
+                .filter_map(|groups| groups.as_array())
+                .flatten()
+                .filter_map(|group| group.get("hooks").and_then(|h| h.as_array()))
+                .flatten()
+                .any(|hook| hook.get("command").and_then(|c| c.as_str()).is_some_and(|c| is_own_hook_command(c, exe_command)))
+        })
+        .unwrap_or(false)
+}
 
-    let settings_configured = if let Ok(content) = fs::read_to_string(&settings_path) {
-        // Check if settings contain our hook path
-        let hook_path = script_path.to_string_lossy();
-        content.contains(&*hook_path) || content.contains("/.protimer/hooks/track-activity.sh")
-    } else {
-        false
+// Commands on our hook events that belong to other tools
+fn find_foreign_hook_commands(settings: &serde_json::Value, exe_command: &str) -> Vec<String> {
+    let mut foreign = Vec::new();
+    for (event, _) in HOOK_EVENTS.iter() {
+        let hooks = settings["hooks"][*event]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|group| group["hooks"].as_array())
+            .flatten();
+        for hook in hooks {
+            if let Some(other) = hook["command"].as_str() {
+                if !is_own_hook_command(other, exe_command)
+                    && !is_legacy_hook_command(other)
+                    && !foreign.iter().any(|f| f == other)
+                {
+                    foreign.push(other.to_string());
+                }
+            }
+        }
+    }
+    foreign
+}
+
+fn check_hooks_status() -> HooksStatus {
+    let settings_path = get_claude_settings_path().ok();
+
+    // The hook is this binary, so it is "installed" whenever we can resolve our own path
+    let hook_command = get_hook_exe_command().ok();
+    let script_installed = hook_command.is_some();
+
+    let settings = settings_path
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok());
+
+    let (settings_configured, foreign_hooks) = match (&hook_command, &settings) {
+        (Some(command), Some(settings)) => (
+            settings_contain_hook_command(settings, command),
+            find_foreign_hook_commands(settings, command),
+        ),
+        _ => (false, Vec::new()),
     };
 
     HooksStatus {
         script_installed,
         settings_configured,
         fully_installed: script_installed && settings_configured,
+        foreign_hooks,
     }
 }
 
-fn do_install_hooks() -> Result<(), String> {
-    let hooks_dir = get_hooks_dir();
-    let script_path = get_hook_script_path();
-    let settings_path = get_claude_settings_path();
-
-    // Create hooks directory
-    fs::create_dir_all(&hooks_dir).map_err(|e| format!("Failed to create hooks directory: {}", e))?;
+fn do_install_hooks() -> Result<(), ProtimerError> {
+    let settings_path = get_claude_settings_path()?;
+    let exe_command = get_hook_exe_command()?;
+    let hook_command = get_hook_command()?;
 
-    // Write hook script
-    let mut file = fs::File::create(&script_path)
-        .map_err(|e| format!("Failed to create hook script: {}", e))?;
-    file.write_all(HOOK_SCRIPT.as_bytes())
-        .map_err(|e| format!("Failed to write hook script: {}", e))?;
-
-    // Make executable (chmod +x)
-    let mut perms = fs::metadata(&script_path)
-        .map_err(|e| format!("Failed to get script metadata: {}", e))?
-        .permissions();
-    perms.set_mode(0o755);
-    fs::set_permissions(&script_path, perms)
-        .map_err(|e| format!("Failed to set script permissions: {}", e))?;
+    // Remove the legacy bash hook script; it needed bash and jq
+    let _ = fs::remove_file(get_hooks_dir()?.join("track-activity.sh"));
 
     // Update Claude settings
     let claude_dir = settings_path.parent().unwrap();
-    fs::create_dir_all(claude_dir).map_err(|e| format!("Failed to create .claude directory: {}", e))?;
-
-    let hook_command = script_path.to_string_lossy().to_string();
+    fs::create_dir_all(claude_dir).map_err(|e| ProtimerError::Io(format!("Failed to create .claude directory: {}", e)))?;
 
     // Read existing settings or create new
     let mut settings: serde_json::Value = if settings_path.exists() {
         let content = fs::read_to_string(&settings_path)
-            .map_err(|e| format!("Failed to read Claude settings: {}", e))?;
+            .map_err(|e| ProtimerError::Io(format!("Failed to read Claude settings: {}", e)))?;
         serde_json::from_str(&content).unwrap_or(serde_json::json!({}))
     } else {
         serde_json::json!({})
@@ -498,31 +1520,48 @@ fn do_install_hooks() -> Result<(), String> {
 
     let hooks = settings.get_mut("hooks").unwrap();
 
-    // Add UserPromptSubmit hook
-    let user_prompt_hook = serde_json::json!([{
-        "hooks": [{ "type": "command", "command": &hook_command }]
-    }]);
-    hooks["UserPromptSubmit"] = user_prompt_hook;
-
-    // Add Stop hook
-    let stop_hook = serde_json::json!([{
-        "matcher": "*",
-        "hooks": [{ "type": "command", "command": &hook_command }]
-    }]);
-    hooks["Stop"] = stop_hook;
-
-    // Add Notification hook for permission_prompt (pauses tracking when waiting for approval)
-    let notification_hook = serde_json::json!([{
-        "matcher": "permission_prompt",
-        "hooks": [{ "type": "command", "command": &hook_command }]
-    }]);
-    hooks["Notification"] = notification_hook;
+    // Merge into each event's hook list so hooks from other tools are preserved
+    for (event, matcher) in HOOK_EVENTS.iter() {
+        let mut groups = hooks[*event].as_array().cloned().unwrap_or_default();
+
+        // Drop entries pointing at the legacy bash script, or at this binary with another data dir,
+        // removing groups left empty
+        for group in groups.iter_mut() {
+            if let Some(entries) = group.get_mut("hooks").and_then(|h| h.as_array_mut()) {
+                entries.retain(|hook| {
+                    !hook["command"].as_str().is_some_and(|command| {
+                        is_legacy_hook_command(command)
+                            || (command != hook_command && is_own_hook_command(command, &exe_command))
+                    })
+                });
+            }
+        }
+        groups.retain(|group| !group["hooks"].as_array().is_some_and(|entries| entries.is_empty()));
+
+        let already_installed = groups.iter().any(|group| {
+            group["hooks"]
+                .as_array()
+                .is_some_and(|entries| entries.iter().any(|hook| hook["command"].as_str() == Some(hook_command.as_str())))
+        });
+
+        if !already_installed {
+            let mut group = serde_json::json!({
+                "hooks": [{ "type": "command", "command": &hook_command }]
+            });
+            if let Some(matcher) = matcher {
+                group["matcher"] = serde_json::json!(matcher);
+            }
+            groups.push(group);
+        }
+
+        hooks[*event] = serde_json::Value::Array(groups);
+    }
 
     // Write updated settings
     let settings_str = serde_json::to_string_pretty(&settings)
-        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        .map_err(|e| ProtimerError::Io(format!("Failed to serialize settings: {}", e)))?;
     fs::write(&settings_path, settings_str)
-        .map_err(|e| format!("Failed to write Claude settings: {}", e))?;
+        .map_err(|e| ProtimerError::Io(format!("Failed to write Claude settings: {}", e)))?;
 
     Ok(())
 }
@@ -535,30 +1574,126 @@ fn check_hooks_installed() -> HooksStatus {
 }
 
 #[tauri::command]
-fn install_hooks() -> Result<HooksStatus, String> {
+fn install_hooks() -> Result<HooksStatus, ProtimerError> {
     do_install_hooks()?;
     Ok(check_hooks_status())
 }
 
+// Streams the file so a large log isn't read into memory; a missing log has no lines
+fn count_activity_log_lines() -> Result<usize, ProtimerError> {
+    let log_path = get_activity_log_path()?;
+    if !log_path.exists() {
+        return Ok(0);
+    }
+
+    let mut file = fs::File::open(&log_path)?;
+    let mut buf = [0u8; 64 * 1024];
+    let mut lines = 0;
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        lines += buf[..read].iter().filter(|b| **b == b'\n').count();
+    }
+    Ok(lines)
+}
+
+#[tauri::command]
+fn get_app_info(state: State<AppState>) -> Result<AppInfo, ProtimerError> {
+    let activity_log_parse_failures = {
+        let mut cache = lock_recover(&state.cache, "activity cache");
+        refresh_activity_cache(&mut cache);
+        cache.parse_failures
+    };
+
+    let schema_version = {
+        let conn = state.readers.get();
+        schema_version(&conn)?
+    };
+
+    Ok(AppInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        data_dir: get_data_dir()?.to_string_lossy().to_string(),
+        schema_version,
+        latest_schema_version: BASELINE_SCHEMA_VERSION + MIGRATIONS.len() as i64,
+        hooks_installed: check_hooks_status().fully_installed,
+        activity_log_lines: count_activity_log_lines()?,
+        activity_log_parse_failures,
+    })
+}
+
+// Columns selected for projects rows, in the order project_from_row expects
+const PROJECT_COLUMNS: &str =
+    "id, name, path, color, hourlyRate, createdAt, archived, matchParents, weeklyGoalMs, dailyGoalMs";
+
+fn project_from_row(row: &rusqlite::Row) -> rusqlite::Result<Project> {
+    Ok(Project {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        path: row.get(2)?,
+        color: row.get(3)?,
+        hourly_rate: row.get(4)?,
+        created_at: row.get(5)?,
+        archived: row.get::<_, i32>(6)? == 1,
+        match_parents: row.get::<_, i32>(7)? == 1,
+        weekly_goal_ms: row.get(8)?,
+        daily_goal_ms: row.get(9)?,
+    })
+}
+
+// Share of the project's weekly goal reached, or of the daily goal when it only has one
+fn goal_progress(project: &Project, today_ms: i64, week_ms: i64) -> Option<f64> {
+    match (project.weekly_goal_ms, project.daily_goal_ms) {
+        (Some(goal), _) if goal > 0 => Some(week_ms as f64 / goal as f64),
+        (_, Some(goal)) if goal > 0 => Some(today_ms as f64 / goal as f64),
+        _ => None,
+    }
+}
+
 #[tauri::command]
-fn get_projects(state: State<AppState>) -> Result<Vec<Project>, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+fn get_projects(include_archived: Option<bool>, state: State<AppState>) -> Result<Vec<Project>, ProtimerError> {
+    let conn = state.readers.get();
     let mut stmt = conn
-        .prepare("SELECT id, name, path, color, hourlyRate, createdAt FROM projects ORDER BY name")
-        .map_err(|e| e.to_string())?;
+        .prepare(&format!("SELECT {} FROM projects WHERE archived = 0 OR ?1 ORDER BY name", PROJECT_COLUMNS))?;
 
     let projects = stmt
-        .query_map([], |row| {
-            Ok(Project {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                path: row.get(2)?,
-                color: row.get(3)?,
-                hourly_rate: row.get(4)?,
-                created_at: row.get(5)?,
+        .query_map(params![include_archived.unwrap_or(false)], project_from_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(projects)
+}
+
+#[tauri::command]
+fn get_projects_with_stats(include_archived: Option<bool>, state: State<AppState>) -> Result<Vec<ProjectWithStats>, ProtimerError> {
+    let conn = state.readers.get();
+    // Totals come from one grouped pass over completed entries rather than a query per project
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, COALESCE(s.totalMs, 0), COALESCE(s.entryCount, 0), s.firstEntryAt, s.lastEntryAt
+         FROM projects
+         LEFT JOIN (
+            SELECT projectId, SUM(endTime - startTime) AS totalMs, COUNT(*) AS entryCount,
+                   MIN(startTime) AS firstEntryAt, MAX(endTime) AS lastEntryAt
+            FROM time_entries
+            WHERE endTime IS NOT NULL AND deletedAt IS NULL
+            GROUP BY projectId
+         ) s ON s.projectId = projects.id
+         WHERE archived = 0 OR ?1
+         ORDER BY name",
+        PROJECT_COLUMNS
+    ))?;
+
+    let projects = stmt
+        .query_map(params![include_archived.unwrap_or(false)], |row| {
+            Ok(ProjectWithStats {
+                project: project_from_row(row)?,
+                total_ms: row.get(10)?,
+                entry_count: row.get(11)?,
+                first_entry_at: row.get(12)?,
+                last_entry_at: row.get(13)?,
             })
-        })
-        .map_err(|e| e.to_string())?
+        })?
         .filter_map(|r| r.ok())
         .collect();
 
@@ -566,18 +1701,53 @@ fn get_projects(state: State<AppState>) -> Result<Vec<Project>, String> {
 }
 
 #[tauri::command]
-fn create_project(name: String, path: String, state: State<AppState>) -> Result<Project, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+fn create_project(
+    name: String,
+    path: String,
+    color: Option<String>,
+    allow_nested: Option<bool>,
+    state: State<AppState>,
+) -> Result<Project, ProtimerError> {
+    if let Some(ref c) = color {
+        validate_color(c)?;
+    }
 
-    // Get color based on project count
-    let count: i32 = conn
-        .query_row("SELECT COUNT(*) FROM projects", [], |row| row.get(0))
-        .unwrap_or(0);
+    let conn = lock_recover(&state.db, "database");
 
-    let colors = [
-        "#FF6B6B", "#4ECDC4", "#45B7D1", "#96CEB4", "#FFEAA7", "#DDA0DD", "#98D8C8", "#F7DC6F",
-    ];
-    let color = colors[count as usize % colors.len()].to_string();
+    // The UNIQUE constraint on path is the backstop; check first so the user gets a readable error
+    let existing_name: Option<String> = conn
+        .query_row("SELECT name FROM projects WHERE path = ?1", params![path], |row| row.get(0))
+        .optional()?;
+    if let Some(existing_name) = existing_name {
+        return Err(ProtimerError::Validation(format!("A project already exists for this folder: {}", existing_name)));
+    }
+
+    // Nesting is allowed (auto-tracking credits the most specific project) but is usually a
+    // mistake, so it needs allow_nested to go through
+    if !allow_nested.unwrap_or(false) {
+        let mut stmt = conn.prepare("SELECT name, path FROM projects")?;
+        let existing: Vec<(String, String)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+        drop(stmt);
+
+        let overlapping = existing.into_iter().find(|(_, existing_path)| {
+            is_path_within_project(&path, existing_path, false) || is_path_within_project(existing_path, &path, false)
+        });
+        if let Some((existing_name, existing_path)) = overlapping {
+            return Err(ProtimerError::Conflict(format!(
+                "This folder overlaps the project {} ({}). Claude sessions will be credited to the more specific one.",
+                existing_name, existing_path
+            )));
+        }
+    }
+
+    // Without an explicit choice, pick the palette color fewest active projects use
+    let color = match color {
+        Some(c) => c,
+        None => pick_project_color(&conn)?,
+    };
 
     let project = Project {
         id: generate_id(),
@@ -586,74 +1756,308 @@ fn create_project(name: String, path: String, state: State<AppState>) -> Result<
         color,
         hourly_rate: None,
         created_at: now_ms(),
+        archived: false,
+        match_parents: false,
+        weekly_goal_ms: None,
+        daily_goal_ms: None,
     };
 
     conn.execute(
         "INSERT INTO projects (id, name, path, color, hourlyRate, createdAt) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
         params![project.id, project.name, project.path, project.color, project.hourly_rate, project.created_at],
-    )
-    .map_err(|e| e.to_string())?;
+    )?;
 
     Ok(project)
 }
 
+// Project colors are #RRGGBB hex strings
+fn validate_color(color: &str) -> Result<(), ProtimerError> {
+    let valid = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if valid {
+        Ok(())
+    } else {
+        Err(ProtimerError::Validation(format!("Invalid color '{}': expected #RRGGBB", color)))
+    }
+}
+
+// Colors handed out to new projects unless the projectPalette setting replaces them
+const DEFAULT_PROJECT_PALETTE: [&str; 8] =
+    ["#FF6B6B", "#4ECDC4", "#45B7D1", "#96CEB4", "#FFEAA7", "#DDA0DD", "#98D8C8", "#F7DC6F"];
+
+// A comma-separated palette as stored in the projectPalette setting, blanks dropped
+fn parse_palette(value: &str) -> Result<Vec<String>, ProtimerError> {
+    let mut palette: Vec<String> = Vec::new();
+    for color in value.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+        validate_color(color)?;
+        let color = color.to_ascii_uppercase();
+        if !palette.contains(&color) {
+            palette.push(color);
+        }
+    }
+    Ok(palette)
+}
+
+// The first palette color no active project uses, else the first of the least used. Depends only
+// on the palette and the colors in use, so the same projects always get the same answer.
+fn pick_project_color(conn: &Connection) -> Result<String, ProtimerError> {
+    // A stored palette was validated by set_setting; fall back to the default if it's unusable anyway
+    let palette = load_setting(conn, SETTING_PROJECT_PALETTE)?
+        .and_then(|value| parse_palette(&value).ok())
+        .filter(|palette| !palette.is_empty())
+        .unwrap_or_else(|| DEFAULT_PROJECT_PALETTE.iter().map(|c| c.to_string()).collect());
+
+    let mut stmt = conn.prepare("SELECT color FROM projects WHERE archived = 0")?;
+    let in_use: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .filter_map(|r| r.ok())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    // min_by_key keeps the first of equal counts, so ties go to palette order
+    let color = palette
+        .into_iter()
+        .min_by_key(|color| in_use.iter().filter(|used| *used == color).count())
+        .unwrap_or_else(|| DEFAULT_PROJECT_PALETTE[0].to_string());
+    Ok(color)
+}
+
 #[tauri::command]
-fn update_project_rate(project_id: String, hourly_rate: Option<f64>, state: State<AppState>) -> Result<(), String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+fn update_project_color(project_id: String, color: String, state: State<AppState>) -> Result<(), ProtimerError> {
+    validate_color(&color)?;
+
+    let conn = lock_recover(&state.db, "database");
     conn.execute(
+        "UPDATE projects SET color = ?1 WHERE id = ?2",
+        params![color, project_id],
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+// Starts a new rate period from now; earlier work keeps the rate it was done at.
+// projects.hourlyRate mirrors the latest period for display and summaries.
+fn update_project_rate(project_id: String, hourly_rate: Option<f64>, state: State<AppState>) -> Result<(), ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
+    let tx = conn.unchecked_transaction()?;
+    let updated = tx.execute(
         "UPDATE projects SET hourlyRate = ?1 WHERE id = ?2",
         params![hourly_rate, project_id],
-    )
-    .map_err(|e| e.to_string())?;
+    )?;
+    if updated == 0 {
+        return Err(ProtimerError::NotFound(format!("Project not found: {}", project_id)));
+    }
+    tx.execute(
+        "INSERT INTO project_rates (projectId, hourlyRate, effectiveFrom) VALUES (?1, ?2, ?3)",
+        params![project_id, hourly_rate, now_ms()],
+    )?;
+    tx.commit()?;
     Ok(())
 }
 
+// A project's rate periods as (effectiveFrom, hourlyRate), oldest first
+fn load_rate_periods(conn: &Connection, project_id: &str) -> rusqlite::Result<Vec<(i64, Option<f64>)>> {
+    let mut stmt = conn.prepare(
+        "SELECT effectiveFrom, hourlyRate FROM project_rates WHERE projectId = ?1 ORDER BY effectiveFrom, id",
+    )?;
+    let periods = stmt
+        .query_map(params![project_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(periods)
+}
+
+// Rate in effect at `time`: the latest period starting at or before it. Work predating every
+// period (e.g. data imported without rate history) falls back to the current rate.
+fn rate_at(periods: &[(i64, Option<f64>)], time: i64, current_rate: Option<f64>) -> Option<f64> {
+    match periods.iter().rev().find(|(effective_from, _)| *effective_from <= time) {
+        Some((_, rate)) => *rate,
+        None => current_rate,
+    }
+}
+
 #[tauri::command]
-fn update_project_name(project_id: String, name: String, state: State<AppState>) -> Result<(), String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+fn update_project_name(project_id: String, name: String, state: State<AppState>) -> Result<(), ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
     conn.execute(
         "UPDATE projects SET name = ?1 WHERE id = ?2",
         params![name, project_id],
-    )
-    .map_err(|e| e.to_string())?;
+    )?;
+    Ok(())
+}
+
+// For when a repo moves on disk. Time entries reference the project id, so they stay attached.
+#[tauri::command]
+fn update_project_path(project_id: String, new_path: String, state: State<AppState>) -> Result<(), ProtimerError> {
+    if new_path.trim().is_empty() {
+        return Err(ProtimerError::Validation("Project path cannot be empty".to_string()));
+    }
+
+    let conn = lock_recover(&state.db, "database");
+
+    // Same readable check as create_project ahead of the UNIQUE constraint
+    let existing_name: Option<String> = conn
+        .query_row(
+            "SELECT name FROM projects WHERE path = ?1 AND id != ?2",
+            params![new_path, project_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(existing_name) = existing_name {
+        return Err(ProtimerError::Validation(format!("A project already exists for this folder: {}", existing_name)));
+    }
+
+    let updated = conn.execute(
+        "UPDATE projects SET path = ?1 WHERE id = ?2",
+        params![new_path, project_id],
+    )?;
+    if updated == 0 {
+        return Err(ProtimerError::NotFound(format!("Project not found: {}", project_id)));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn update_project_client(
+    project_id: String,
+    name: Option<String>,
+    email: Option<String>,
+    address: Option<String>,
+    state: State<AppState>,
+) -> Result<(), ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
+
+    // Store blanks as NULL so invoices fall back to the project name
+    let clean = |value: Option<String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+    conn.execute(
+        "UPDATE projects SET clientName = ?1, clientEmail = ?2, clientAddress = ?3 WHERE id = ?4",
+        params![clean(name), clean(email), clean(address), project_id],
+    )?;
+    Ok(())
+}
+
+// Reference the client wants on their invoices, such as a PO number; printed as "Ref: {note}".
+// None or blank removes it.
+#[tauri::command]
+fn set_project_invoice_note(project_id: String, note: Option<String>, state: State<AppState>) -> Result<(), ProtimerError> {
+    let note = note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+
+    let conn = lock_recover(&state.db, "database");
+    let updated = conn.execute(
+        "UPDATE projects SET invoiceHeaderNote = ?1 WHERE id = ?2",
+        params![note, project_id],
+    )?;
+    if updated == 0 {
+        return Err(ProtimerError::NotFound(format!("Project not found: {}", project_id)));
+    }
+    Ok(())
+}
+
+// Target tracked time for the project; None clears that goal
+#[tauri::command]
+fn set_project_goals(
+    project_id: String,
+    weekly_goal_ms: Option<i64>,
+    daily_goal_ms: Option<i64>,
+    state: State<AppState>,
+) -> Result<(), ProtimerError> {
+    if weekly_goal_ms.is_some_and(|ms| ms <= 0) || daily_goal_ms.is_some_and(|ms| ms <= 0) {
+        return Err(ProtimerError::Validation("Goals must be greater than zero".to_string()));
+    }
+
+    let conn = lock_recover(&state.db, "database");
+    let updated = conn.execute(
+        "UPDATE projects SET weeklyGoalMs = ?1, dailyGoalMs = ?2 WHERE id = ?3",
+        params![weekly_goal_ms, daily_goal_ms, project_id],
+    )?;
+    if updated == 0 {
+        return Err(ProtimerError::NotFound(format!("Project not found: {}", project_id)));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn set_project_match_parents(project_id: String, match_parents: bool, state: State<AppState>) -> Result<(), ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
+    conn.execute(
+        "UPDATE projects SET matchParents = ?1 WHERE id = ?2",
+        params![if match_parents { 1 } else { 0 }, project_id],
+    )?;
+    Ok(())
+}
+
+// Hide a finished project from the active list while keeping its history
+#[tauri::command]
+fn set_project_archived(project_id: String, archived: bool, state: State<AppState>) -> Result<(), ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
+    conn.execute(
+        "UPDATE projects SET archived = ?1 WHERE id = ?2",
+        params![if archived { 1 } else { 0 }, project_id],
+    )?;
     Ok(())
 }
 
 #[tauri::command]
-fn delete_project(project_id: String, state: State<AppState>) -> Result<(), String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+fn delete_project(project_id: String, state: State<AppState>) -> Result<(), ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
 
     // Delete all related data first (foreign key constraints)
     conn.execute("DELETE FROM time_entries WHERE projectId = ?1", params![project_id])
-        .map_err(|e| format!("Failed to delete time entries: {}", e))?;
+        .map_err(|e| ProtimerError::Database(format!("Failed to delete time entries: {}", e)))?;
     conn.execute("DELETE FROM active_sessions WHERE projectId = ?1", params![project_id])
-        .map_err(|e| format!("Failed to delete active sessions: {}", e))?;
+        .map_err(|e| ProtimerError::Database(format!("Failed to delete active sessions: {}", e)))?;
+    conn.execute(
+        "DELETE FROM invoice_entries WHERE invoiceId IN (SELECT id FROM invoices WHERE projectId = ?1)",
+        params![project_id],
+    )
+    .map_err(|e| ProtimerError::Database(format!("Failed to delete invoice entries: {}", e)))?;
     conn.execute("DELETE FROM invoices WHERE projectId = ?1", params![project_id])
-        .map_err(|e| format!("Failed to delete invoices: {}", e))?;
+        .map_err(|e| ProtimerError::Database(format!("Failed to delete invoices: {}", e)))?;
+    conn.execute("DELETE FROM project_rates WHERE projectId = ?1", params![project_id])
+        .map_err(|e| ProtimerError::Database(format!("Failed to delete rate history: {}", e)))?;
     conn.execute("DELETE FROM projects WHERE id = ?1", params![project_id])
-        .map_err(|e| format!("Failed to delete project: {}", e))?;
+        .map_err(|e| ProtimerError::Database(format!("Failed to delete project: {}", e)))?;
 
     Ok(())
 }
 
 #[tauri::command]
-fn start_tracking(project_id: String, manual_mode: bool, state: State<AppState>) -> Result<ActiveSession, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+fn start_tracking(project_id: String, manual_mode: bool, state: State<AppState>) -> Result<ActiveSession, ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
+    start_session(&conn, project_id, manual_mode)
+}
+
+// Start tracking whichever project contains cwd, e.g. from a terminal in the repo.
+// When projects are nested, the most specific one wins, as in auto-tracking.
+#[tauri::command]
+fn start_tracking_by_path(cwd: String, manual_mode: bool, state: State<AppState>) -> Result<ActiveSession, ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
+
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM projects WHERE archived = 0", PROJECT_COLUMNS))?;
+    let projects: Vec<Project> = stmt
+        .query_map([], project_from_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+    drop(stmt);
 
+    let project_id = owning_project(&cwd, &projects)
+        .map(|p| p.id.clone())
+        .ok_or_else(|| ProtimerError::NotFound(format!("No project found for {}", cwd)))?;
+
+    start_session(&conn, project_id, manual_mode)
+}
+
+// Shared by start_tracking and start_tracking_by_path. Starting a project that is already
+// tracked returns the existing session, upgraded to manual mode if requested.
+fn start_session(conn: &Connection, project_id: String, manual_mode: bool) -> Result<ActiveSession, ProtimerError> {
     // Check if already tracking
     let existing: Option<ActiveSession> = conn
         .query_row(
-            "SELECT projectId, startTime, claudeCodeDetected, lastClaudeCheck, manualMode FROM active_sessions WHERE projectId = ?1",
+            &format!("SELECT {} FROM active_sessions WHERE projectId = ?1", ACTIVE_SESSION_COLUMNS),
             params![project_id],
-            |row| {
-                Ok(ActiveSession {
-                    project_id: row.get(0)?,
-                    start_time: row.get(1)?,
-                    claude_code_detected: row.get::<_, i32>(2)? == 1,
-                    last_claude_check: row.get(3)?,
-                    manual_mode: row.get::<_, i32>(4)? == 1,
-                })
-            },
+            active_session_from_row,
         )
         .ok();
 
@@ -662,8 +2066,7 @@ fn start_tracking(project_id: String, manual_mode: bool, state: State<AppState>)
             conn.execute(
                 "UPDATE active_sessions SET manualMode = 1 WHERE projectId = ?1",
                 params![project_id],
-            )
-            .map_err(|e| e.to_string())?;
+            )?;
             session.manual_mode = true;
         }
         return Ok(session);
@@ -676,35 +2079,31 @@ fn start_tracking(project_id: String, manual_mode: bool, state: State<AppState>)
         claude_code_detected: false,
         last_claude_check: now,
         manual_mode,
+        paused_ms: 0,
+        paused_at: None,
+        idle_paused: false,
+        pending_description: None,
     };
 
     conn.execute(
         "INSERT OR REPLACE INTO active_sessions (projectId, startTime, claudeCodeDetected, lastClaudeCheck, manualMode) VALUES (?1, ?2, ?3, ?4, ?5)",
         params![session.project_id, session.start_time, 0, session.last_claude_check, if manual_mode { 1 } else { 0 }],
-    )
-    .map_err(|e| e.to_string())?;
+    )?;
 
     Ok(session)
 }
 
+// The entry takes description when given, otherwise the session's pending description
 #[tauri::command]
-fn stop_tracking(project_id: String, state: State<AppState>) -> Result<Option<TimeEntry>, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+fn stop_tracking(project_id: String, description: Option<String>, state: State<AppState>) -> Result<Option<TimeEntry>, ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
 
     // Get active session
     let session: Option<ActiveSession> = conn
         .query_row(
-            "SELECT projectId, startTime, claudeCodeDetected, lastClaudeCheck, manualMode FROM active_sessions WHERE projectId = ?1",
+            &format!("SELECT {} FROM active_sessions WHERE projectId = ?1", ACTIVE_SESSION_COLUMNS),
             params![project_id],
-            |row| {
-                Ok(ActiveSession {
-                    project_id: row.get(0)?,
-                    start_time: row.get(1)?,
-                    claude_code_detected: row.get::<_, i32>(2)? == 1,
-                    last_claude_check: row.get(3)?,
-                    manual_mode: row.get::<_, i32>(4)? == 1,
-                })
-            },
+            active_session_from_row,
         )
         .ok();
 
@@ -713,60 +2112,434 @@ fn stop_tracking(project_id: String, state: State<AppState>) -> Result<Option<Ti
         None => return Ok(None),
     };
 
-    let actual_end_time = now_ms();
+    // A paused session ends when it was paused. Paused stretches are excluded by
+    // anchoring the entry's duration to the time actually tracked.
+    let actual_end_time = session.paused_at.unwrap_or_else(now_ms);
+    let tracked_ms = session.elapsed_ms(actual_end_time);
 
     let entry = TimeEntry {
         id: generate_id(),
         project_id: project_id.clone(),
-        start_time: session.start_time,
+        start_time: actual_end_time - tracked_ms,
         end_time: Some(actual_end_time),
         claude_code_active: session.claude_code_detected,
-        description: None,
+        description: description
+            .map(|d| d.trim().to_string())
+            .filter(|d| !d.is_empty())
+            .or(session.pending_description),
+        tags: Vec::new(),
+        invoice_id: None,
     };
 
     conn.execute(
         "INSERT INTO time_entries (id, projectId, startTime, endTime, claudeCodeActive, description) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
         params![entry.id, entry.project_id, entry.start_time, entry.end_time, if entry.claude_code_active { 1 } else { 0 }, entry.description],
-    )
-    .map_err(|e| e.to_string())?;
+    )?;
 
-    conn.execute("DELETE FROM active_sessions WHERE projectId = ?1", params![project_id])
-        .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM active_sessions WHERE projectId = ?1", params![project_id])?;
 
     Ok(Some(entry))
 }
 
-#[tauri::command]
-fn get_status(state: State<AppState>) -> Result<Status, String> {
-    let cached_entries = {
-        let mut cache = state.cache.lock().map_err(|e| e.to_string())?;
+fn get_active_session(conn: &Connection, project_id: &str) -> Option<ActiveSession> {
+    conn.query_row(
+        &format!("SELECT {} FROM active_sessions WHERE projectId = ?1", ACTIVE_SESSION_COLUMNS),
+        params![project_id],
+        active_session_from_row,
+    )
+    .ok()
+}
+
+// Running sessions refresh lastClaudeCheck at most this often while the app polls get_status,
+// so it records when the session was last seen with the app open
+const SESSION_HEARTBEAT_MS: i64 = 60 * 1000;
+
+// A running manual session whose heartbeat is older than this at launch was left going while
+// the app was closed (or the machine slept)
+const STALE_SESSION_THRESHOLD_MS: i64 = 30 * 60 * 1000;
+
+// Close stale manual sessions at their last heartbeat so quitting overnight doesn't produce
+// a phantom entry covering the hours the app was closed. Paused sessions aren't accruing, so
+// they're left alone. Returns how many sessions were closed.
+fn close_stale_manual_sessions(conn: &Connection, now: i64) -> rusqlite::Result<usize> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM active_sessions WHERE manualMode = 1 AND pausedAt IS NULL AND lastClaudeCheck < ?1",
+        ACTIVE_SESSION_COLUMNS
+    ))?;
+    let stale: Vec<ActiveSession> = stmt
+        .query_map(params![now - STALE_SESSION_THRESHOLD_MS], active_session_from_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let tx = conn.unchecked_transaction()?;
+    for session in &stale {
+        let end_time = session.last_claude_check.max(session.start_time);
+        let start_time = end_time - session.elapsed_ms(end_time);
+        if end_time > start_time {
+            tx.execute(
+                "INSERT INTO time_entries (id, projectId, startTime, endTime, claudeCodeActive, description) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![generate_id(), session.project_id, start_time, end_time, if session.claude_code_detected { 1 } else { 0 }, session.pending_description],
+            )?;
+        }
+        tx.execute("DELETE FROM active_sessions WHERE projectId = ?1", params![session.project_id])?;
+    }
+    tx.commit()?;
+
+    Ok(stale.len())
+}
+
+// Sessions not seen running (per the heartbeat above) for max_age_ms, e.g. left behind by a
+// crash, so the UI can offer to stop or discard them
+#[tauri::command]
+fn list_stale_sessions(max_age_ms: i64, state: State<AppState>) -> Result<Vec<ActiveSession>, ProtimerError> {
+    if max_age_ms < 0 {
+        return Err(ProtimerError::Validation("max_age_ms must not be negative".to_string()));
+    }
+
+    let conn = state.readers.get();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM active_sessions WHERE lastClaudeCheck < ?1 ORDER BY startTime",
+        ACTIVE_SESSION_COLUMNS
+    ))?;
+    let sessions = stmt
+        .query_map(params![now_ms() - max_age_ms], active_session_from_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(sessions)
+}
+
+// Drop an active session without recording it; stop_tracking is the variant that writes an entry
+#[tauri::command]
+fn discard_session(project_id: String, state: State<AppState>) -> Result<(), ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
+
+    let deleted = conn.execute("DELETE FROM active_sessions WHERE projectId = ?1", params![project_id])?;
+    if deleted == 0 {
+        return Err(ProtimerError::NotFound("Project is not currently tracking".to_string()));
+    }
+
+    Ok(())
+}
+
+// Note what the running session is for; it becomes the entry's description however the
+// session ends, including auto-stop. None or blank clears it.
+#[tauri::command]
+fn set_pending_description(project_id: String, description: Option<String>, state: State<AppState>) -> Result<(), ProtimerError> {
+    let description = description.map(|d| d.trim().to_string()).filter(|d| !d.is_empty());
+
+    let conn = lock_recover(&state.db, "database");
+    let updated = conn.execute(
+        "UPDATE active_sessions SET pendingDescription = ?1 WHERE projectId = ?2",
+        params![description, project_id],
+    )?;
+    if updated == 0 {
+        return Err(ProtimerError::NotFound("Project is not currently tracking".to_string()));
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+fn pause_tracking(project_id: String, state: State<AppState>) -> Result<ActiveSession, ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
+
+    let mut session = get_active_session(&conn, &project_id)
+        .ok_or(ProtimerError::NotFound("Project is not currently tracking".to_string()))?;
+
+    if session.paused_at.is_some() {
+        // Already paused for idleness; pausing explicitly keeps it paused when input returns
+        if session.idle_paused {
+            session.idle_paused = false;
+            conn.execute("UPDATE active_sessions SET idlePaused = 0 WHERE projectId = ?1", params![project_id])?;
+        }
+        return Ok(session);
+    }
+
+    // Bank the running stretch so elapsed time stops counting while paused
+    let now = now_ms();
+    session.paused_ms = session.elapsed_ms(now);
+    session.paused_at = Some(now);
+
+    conn.execute(
+        "UPDATE active_sessions SET pausedMs = ?1, pausedAt = ?2 WHERE projectId = ?3",
+        params![session.paused_ms, session.paused_at, project_id],
+    )?;
+
+    Ok(session)
+}
+
+#[tauri::command]
+fn resume_tracking(project_id: String, state: State<AppState>) -> Result<ActiveSession, ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
+
+    let mut session = get_active_session(&conn, &project_id)
+        .ok_or(ProtimerError::NotFound("Project is not currently tracking".to_string()))?;
+
+    if session.paused_at.is_none() {
+        return Ok(session);
+    }
+
+    // Start a fresh running stretch; banked time stays in pausedMs
+    session.start_time = now_ms();
+    session.last_claude_check = session.start_time;
+    session.paused_at = None;
+    session.idle_paused = false;
+
+    conn.execute(
+        "UPDATE active_sessions SET startTime = ?1, lastClaudeCheck = ?1, pausedAt = NULL, idlePaused = 0 WHERE projectId = ?2",
+        params![session.start_time, project_id],
+    )?;
+
+    Ok(session)
+}
+
+// Take over an auto-tracked session so Claude stopping no longer closes it. The original
+// start time and any banked paused time are kept.
+#[tauri::command]
+fn promote_to_manual(project_id: String, state: State<AppState>) -> Result<ActiveSession, ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
+
+    let mut session = get_active_session(&conn, &project_id)
+        .ok_or(ProtimerError::NotFound("Project is not currently tracking".to_string()))?;
+
+    if session.manual_mode {
+        return Ok(session);
+    }
+
+    // An idle pause belongs to auto-tracking; a manual session stays paused until resumed by hand
+    session.manual_mode = true;
+    session.idle_paused = false;
+
+    conn.execute(
+        "UPDATE active_sessions SET manualMode = 1, idlePaused = 0 WHERE projectId = ?1",
+        params![project_id],
+    )?;
+
+    Ok(session)
+}
+
+// Move a project's most recent completed entry back into active_sessions so tracking continues
+#[tauri::command]
+fn reopen_entry(entry_id: String, state: State<AppState>) -> Result<ActiveSession, ProtimerError> {
+    let mut conn = lock_recover(&state.db, "database");
+
+    let (project_id, start_time, claude_code_active, description): (String, i64, bool, Option<String>) = conn
+        .query_row(
+            "SELECT projectId, startTime, claudeCodeActive, description FROM time_entries
+             WHERE id = ?1 AND endTime IS NOT NULL AND deletedAt IS NULL",
+            params![entry_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, i32>(2)? == 1, row.get(3)?)),
+        )
+        .map_err(|e| ProtimerError::NotFound(format!("Completed entry not found: {}", e)))?;
+
+    if get_active_session(&conn, &project_id).is_some() {
+        return Err(ProtimerError::Validation("Project is already tracking; stop the current session before reopening an entry".to_string()));
+    }
+
+    let latest_id: String = conn
+        .query_row(
+            "SELECT id FROM time_entries WHERE projectId = ?1 AND deletedAt IS NULL ORDER BY startTime DESC LIMIT 1",
+            params![project_id],
+            |row| row.get(0),
+        )?;
+    if latest_id != entry_id {
+        return Err(ProtimerError::Validation("Only the most recent entry for a project can be reopened".to_string()));
+    }
+
+    // Reopened sessions are manual so hook-driven auto-stop doesn't close them straight away
+    let now = now_ms();
+    let session = ActiveSession {
+        project_id: project_id.clone(),
+        start_time,
+        claude_code_detected: claude_code_active,
+        last_claude_check: now,
+        manual_mode: true,
+        paused_ms: 0,
+        paused_at: None,
+        idle_paused: false,
+        // Kept so stopping again doesn't lose what the entry said
+        pending_description: description,
+    };
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO active_sessions (projectId, startTime, claudeCodeDetected, lastClaudeCheck, manualMode, pendingDescription) VALUES (?1, ?2, ?3, ?4, 1, ?5)",
+        params![session.project_id, session.start_time, if claude_code_active { 1 } else { 0 }, session.last_claude_check, session.pending_description],
+    )?;
+    tx.execute("DELETE FROM time_entries WHERE id = ?1", params![entry_id])?;
+    tx.commit()?;
+
+    Ok(session)
+}
+
+// A session change decided by compute_status's auto-tracking pass
+enum SessionTransition {
+    // Claude became active in a project with no session
+    Start,
+    // End the session, recording start_time..end_time when it isn't empty
+    Close {
+        start_time: i64,
+        end_time: i64,
+        claude_code_active: bool,
+        description: Option<String>,
+    },
+    // The user went idle during an auto session; bank tracked time and pause
+    IdlePause { paused_ms: i64, paused_at: i64 },
+    // Input returned after an idle pause; start a new running stretch
+    IdleResume { start_time: i64 },
+}
+
+fn apply_session_transitions(
+    conn: &Connection,
+    transitions: &[(String, SessionTransition)],
+    heartbeats: &[String],
+    now: i64,
+) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+
+    for (project_id, transition) in transitions {
+        match transition {
+            SessionTransition::Start => {
+                tx.execute(
+                    "INSERT INTO active_sessions (projectId, startTime, claudeCodeDetected, lastClaudeCheck, manualMode) VALUES (?1, ?2, 1, ?2, 0)",
+                    params![project_id, now],
+                )?;
+            }
+            SessionTransition::Close { start_time, end_time, claude_code_active, description } => {
+                if end_time > start_time {
+                    tx.execute(
+                        "INSERT INTO time_entries (id, projectId, startTime, endTime, claudeCodeActive, description) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                        params![generate_id(), project_id, start_time, end_time, if *claude_code_active { 1 } else { 0 }, description],
+                    )?;
+                }
+                tx.execute("DELETE FROM active_sessions WHERE projectId = ?1", params![project_id])?;
+            }
+            SessionTransition::IdlePause { paused_ms, paused_at } => {
+                tx.execute(
+                    "UPDATE active_sessions SET pausedMs = ?1, pausedAt = ?2, idlePaused = 1 WHERE projectId = ?3",
+                    params![paused_ms, paused_at, project_id],
+                )?;
+            }
+            SessionTransition::IdleResume { start_time } => {
+                tx.execute(
+                    "UPDATE active_sessions SET startTime = ?1, lastClaudeCheck = ?1, pausedAt = NULL, idlePaused = 0 WHERE projectId = ?2",
+                    params![start_time, project_id],
+                )?;
+            }
+        }
+    }
+
+    for project_id in heartbeats {
+        tx.execute(
+            "UPDATE active_sessions SET lastClaudeCheck = ?1 WHERE projectId = ?2",
+            params![now, project_id],
+        )?;
+    }
+
+    tx.commit()
+}
+
+// Active sessions for the given projects in a single query
+fn load_active_sessions(conn: &Connection, project_ids: &[&str]) -> rusqlite::Result<Vec<ActiveSession>> {
+    if project_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let placeholders: Vec<String> = (1..=project_ids.len()).map(|i| format!("?{}", i)).collect();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM active_sessions WHERE projectId IN ({})",
+        ACTIVE_SESSION_COLUMNS,
+        placeholders.join(", ")
+    ))?;
+    let sessions = stmt
+        .query_map(rusqlite::params_from_iter(project_ids), active_session_from_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(sessions)
+}
+
+// Reconcile sessions, then push the fresh Status to the frontend, saving it a get_status round-trip
+fn push_status(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<AppState>();
+    match compute_status(app_handle, false, true) {
+        Ok(status) => {
+            let _ = app_handle.emit("status-updated", status);
+        }
+        Err(e) => eprintln!("Failed to compute status: {}", e),
+    }
+
+    // Warn the frontend once per run that activity lines are being dropped; the count
+    // stays available from get_app_info
+    let unreported_failures = {
+        let mut cache = lock_recover(&state.cache, "activity cache");
+        if cache.parse_failures > 0 && !cache.parse_failures_reported {
+            cache.parse_failures_reported = true;
+            Some(cache.parse_failures)
+        } else {
+            None
+        }
+    };
+    if let Some(count) = unreported_failures {
+        let _ = app_handle.emit("activity-log-parse-failures", count);
+    }
+}
+
+// Read-only unless reconcile is set; the activity watcher and periodic push already reconcile
+#[tauri::command]
+fn get_status(include_archived: Option<bool>, reconcile: Option<bool>, app_handle: tauri::AppHandle) -> Result<Status, ProtimerError> {
+    compute_status(&app_handle, include_archived.unwrap_or(false), reconcile.unwrap_or(false))
+}
+
+// Run auto-tracking now: start, stop, pause or resume sessions from Claude activity and
+// idleness, and return the settled status
+#[tauri::command]
+fn reconcile_sessions(app_handle: tauri::AppHandle) -> Result<Status, ProtimerError> {
+    compute_status(&app_handle, false, true)
+}
+
+// Sent as tracking-auto-started / tracking-auto-stopped when auto-tracking changes a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackingEvent {
+    pub project_id: String,
+    // When the session started, or the (possibly backdated) time it ended
+    pub timestamp: i64,
+}
+
+// Shared by get_status, reconcile_sessions and the status-updated push. Only reconcile writes:
+// it applies auto-tracking session changes and heartbeats before the status is built.
+fn compute_status(app_handle: &tauri::AppHandle, include_archived: bool, reconcile: bool) -> Result<Status, ProtimerError> {
+    let state = app_handle.state::<AppState>();
+    let cached_entries = {
+        let mut cache = lock_recover(&state.cache, "activity cache");
         refresh_activity_cache(&mut cache);
         Arc::clone(&cache.entries)
     };
 
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    let system_idle_time = {
+        let mut idle_cache = lock_recover(&state.idle_cache, "idle cache");
+        refresh_system_idle_cache(&mut idle_cache)
+    };
+
+    let conn = if reconcile { lock_recover(&state.db, "database") } else { state.readers.get() };
 
     let now = now_ms();
     let today_start = get_today_start_ms();
     let week_start = get_week_start_ms();
-
-    // BULK QUERY 1: Get all projects
+    let business = load_business_info(&conn).ok();
+    let idle_threshold_ms = business.as_ref().map(|b| b.idle_threshold_ms).unwrap_or(DEFAULT_IDLE_THRESHOLD_MS);
+    let idle_known = system_idle_time != IDLE_TIME_UNKNOWN;
+    let is_idle = idle_known && idle_threshold_ms > 0 && system_idle_time > idle_threshold_ms;
+    let pause_auto_on_idle = business.as_ref().is_some_and(|b| b.pause_auto_on_idle);
+
+    // BULK QUERY 1: Get all projects. Archived ones are only listed with include_archived, and
+    // even then never own Claude sessions or get auto-tracked.
     let mut stmt = conn
-        .prepare("SELECT id, name, path, color, hourlyRate, createdAt FROM projects ORDER BY name")
-        .map_err(|e| e.to_string())?;
+        .prepare(&format!("SELECT {} FROM projects WHERE archived = 0 OR ?1 ORDER BY name", PROJECT_COLUMNS))?;
 
     let projects: Vec<Project> = stmt
-        .query_map([], |row| {
-            Ok(Project {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                path: row.get(2)?,
-                color: row.get(3)?,
-                hourly_rate: row.get(4)?,
-                created_at: row.get(5)?,
-            })
-        })
-        .map_err(|e| e.to_string())?
+        .query_map(params![include_archived], project_from_row)?
         .filter_map(|r| r.ok())
         .collect();
 
@@ -774,39 +2547,29 @@ fn get_status(state: State<AppState>) -> Result<Status, String> {
     let mut sessions_map: std::collections::HashMap<String, ActiveSession> = std::collections::HashMap::new();
     {
         let mut stmt = conn
-            .prepare("SELECT projectId, startTime, claudeCodeDetected, lastClaudeCheck, manualMode FROM active_sessions")
-            .map_err(|e| e.to_string())?;
+            .prepare(&format!("SELECT {} FROM active_sessions", ACTIVE_SESSION_COLUMNS))?;
         let sessions = stmt
-            .query_map([], |row| {
-                Ok(ActiveSession {
-                    project_id: row.get(0)?,
-                    start_time: row.get(1)?,
-                    claude_code_detected: row.get::<_, i32>(2)? == 1,
-                    last_claude_check: row.get(3)?,
-                    manual_mode: row.get::<_, i32>(4)? == 1,
-                })
-            })
-            .map_err(|e| e.to_string())?;
+            .query_map([], active_session_from_row)?;
         for session in sessions.filter_map(|r| r.ok()) {
             sessions_map.insert(session.project_id.clone(), session);
         }
     }
 
     // BULK QUERY 3: Get all time aggregates in ONE query
-    // Returns: projectId, today_time, week_time, total_time
-    let mut time_map: std::collections::HashMap<String, (i64, i64, i64)> = std::collections::HashMap::new();
+    // Returns: projectId, today_time, week_time, total_time, claude_time
+    let mut time_map: std::collections::HashMap<String, (i64, i64, i64, i64)> = std::collections::HashMap::new();
     {
         let mut stmt = conn
             .prepare(
                 "SELECT projectId,
                     COALESCE(SUM(CASE WHEN startTime >= ?1 THEN endTime - startTime ELSE 0 END), 0) as today_time,
                     COALESCE(SUM(CASE WHEN startTime >= ?2 THEN endTime - startTime ELSE 0 END), 0) as week_time,
-                    COALESCE(SUM(endTime - startTime), 0) as total_time
+                    COALESCE(SUM(endTime - startTime), 0) as total_time,
+                    COALESCE(SUM(CASE WHEN claudeCodeActive = 1 THEN endTime - startTime ELSE 0 END), 0) as claude_time
                  FROM time_entries
-                 WHERE endTime IS NOT NULL
+                 WHERE endTime IS NOT NULL AND deletedAt IS NULL
                  GROUP BY projectId"
-            )
-            .map_err(|e| e.to_string())?;
+            )?;
         let times = stmt
             .query_map(params![today_start, week_start], |row| {
                 Ok((
@@ -814,109 +2577,190 @@ fn get_status(state: State<AppState>) -> Result<Status, String> {
                     row.get::<_, i64>(1)?,
                     row.get::<_, i64>(2)?,
                     row.get::<_, i64>(3)?,
+                    row.get::<_, i64>(4)?,
                 ))
-            })
-            .map_err(|e| e.to_string())?;
+            })?;
         for time in times.filter_map(|r| r.ok()) {
-            time_map.insert(time.0, (time.1, time.2, time.3));
+            time_map.insert(time.0, (time.1, time.2, time.3, time.4));
         }
     }
 
-    // BULK QUERY 4: Get total claude time (single query)
-    let claude_total: i64 = conn
+    // BULK QUERY 4: Get total claude time (single query). Live Claude sessions are added per project below.
+    let mut claude_total: i64 = conn
         .query_row(
-            "SELECT COALESCE(SUM(CASE WHEN endTime IS NULL THEN ?1 - startTime ELSE endTime - startTime END), 0) FROM time_entries WHERE claudeCodeActive = 1",
+            "SELECT COALESCE(SUM(CASE WHEN endTime IS NULL THEN ?1 - startTime ELSE endTime - startTime END), 0) FROM time_entries WHERE claudeCodeActive = 1 AND deletedAt IS NULL",
             params![now],
             |row| row.get(0),
         )
         .unwrap_or(0);
 
-    let mut project_statuses = Vec::new();
-    let mut today_total: i64 = 0;
+    // Index the log once instead of rescanning every entry for each project
+    let session_activity = index_activity_entries(&cached_entries);
+    let active_projects: Vec<Project> = projects.iter().filter(|p| !p.archived).cloned().collect();
+    let cwd_owners = index_cwd_owners(&session_activity, &active_projects);
+
+    // Pass 1: read Claude state for every project and decide session changes without writing
+    let mut scanned: Vec<(Project, Vec<ClaudeSession>, i32)> = Vec::with_capacity(projects.len());
+    let mut transitions: Vec<(String, SessionTransition)> = Vec::new();
+    let mut heartbeats: Vec<String> = Vec::new();
 
     for project in projects {
-        // Get Claude state from activity log (hooks are the source of truth for starting)
-        let claude_sessions = get_claude_sessions_for_project_cached(&project.path, &cached_entries);
-        let hook_says_active = claude_sessions.iter().any(|(_, state, _)| state == "active");
+        // Archived projects are reported as they stand
+        if project.archived {
+            scanned.push((project, Vec::new(), 0));
+            continue;
+        }
 
-        // Hooks are source of truth for both display and tracking
-        let claude_is_active = hook_says_active;
-        let claude_state = if claude_is_active { "active" } else { "stopped" };
-        let claude_session_count = if claude_is_active { 1 } else { 0 };
+        // Get Claude state from activity log (hooks are the source of truth for starting)
+        let claude_sessions = get_claude_sessions_for_project_cached(&project.id, &cwd_owners, &session_activity);
+        let claude_session_count = claude_sessions.iter().filter(|s| s.state == "active").count() as i32;
+        let hook_says_active = claude_session_count > 0;
 
         // Get active session from pre-fetched map
-        let active_session = sessions_map.get(&project.id).cloned();
-        let manual_mode = active_session.as_ref().map(|s| s.manual_mode).unwrap_or(false);
+        let active_session = sessions_map.get(&project.id);
+        let manual_mode = active_session.map(|s| s.manual_mode).unwrap_or(false);
 
         // Auto-tracking: start/stop based on Claude activity (only for non-manual sessions)
-        let mut session_changed = false;
-        if hook_says_active && active_session.is_none() {
+        let transition = if hook_says_active && active_session.is_none() {
             // Hook says active (UserPromptSubmit received) - auto-start tracking
-            let _ = conn.execute(
-                "INSERT INTO active_sessions (projectId, startTime, claudeCodeDetected, lastClaudeCheck, manualMode) VALUES (?1, ?2, 1, ?2, 0)",
-                params![project.id, now],
-            );
-            session_changed = true;
-        } else if active_session.is_some() && !manual_mode {
+            Some(SessionTransition::Start)
+        } else if let Some(session) = active_session.filter(|_| !manual_mode && !hook_says_active) {
             // Hooks are source of truth - only stop when hooks say stopped.
             // Process detection is unreliable (pgrep gaps cause flickering).
             // Stale sessions (no hook events for 10 min) are already handled by
             // get_claude_sessions_for_project_cached marking them as "stopped".
-            let should_stop = !hook_says_active;
-            if should_stop {
-                if let Some(ref session) = active_session {
-                    let entry_id = uuid::Uuid::new_v4().to_string();
-                    let _ = conn.execute(
-                        "INSERT INTO time_entries (id, projectId, startTime, endTime, claudeCodeActive, description) VALUES (?1, ?2, ?3, ?4, 1, '')",
-                        params![entry_id, project.id, session.start_time, now],
-                    );
-                    let _ = conn.execute(
-                        "DELETE FROM active_sessions WHERE projectId = ?1",
-                        params![project.id],
-                    );
-                    session_changed = true;
+            let end_time = session.paused_at.unwrap_or(now);
+            Some(SessionTransition::Close {
+                start_time: end_time - session.elapsed_ms(end_time),
+                end_time,
+                claude_code_active: true,
+                description: session.pending_description.clone(),
+            })
+        } else if manual_mode && is_idle {
+            // Manual sessions don't stop on their own - close them once the user has walked away,
+            // backdating the end to when idleness began
+            active_session.filter(|s| s.paused_at.is_none()).map(|session| {
+                let end_time = (now - system_idle_time).max(session.start_time);
+                SessionTransition::Close {
+                    start_time: end_time - session.elapsed_ms(end_time),
+                    end_time,
+                    claude_code_active: session.claude_code_detected,
+                    description: session.pending_description.clone(),
                 }
+            })
+        } else if let Some(session) = active_session.filter(|_| !manual_mode && pause_auto_on_idle) {
+            // Claude may still be working while the user is away; stop accruing from when idleness
+            // began and pick up again once input returns, so idle gaps never reach the entry
+            if is_idle && session.paused_at.is_none() {
+                let paused_at = (now - system_idle_time).max(session.start_time);
+                Some(SessionTransition::IdlePause {
+                    paused_ms: session.elapsed_ms(paused_at),
+                    paused_at,
+                })
+            } else if idle_known && !is_idle && session.idle_paused {
+                let paused_at = session.paused_at.unwrap_or(now);
+                Some(SessionTransition::IdleResume {
+                    start_time: (now - system_idle_time).max(paused_at).min(now),
+                })
+            } else {
+                None
             }
-        }
-
-        // Only re-fetch if we changed the session
-        let final_session = if session_changed {
-            conn.query_row(
-                "SELECT projectId, startTime, claudeCodeDetected, lastClaudeCheck, manualMode FROM active_sessions WHERE projectId = ?1",
-                params![project.id],
-                |row| {
-                    Ok(ActiveSession {
-                        project_id: row.get(0)?,
-                        start_time: row.get(1)?,
-                        claude_code_detected: row.get::<_, i32>(2)? == 1,
-                        last_claude_check: row.get(3)?,
-                        manual_mode: row.get::<_, i32>(4)? == 1,
-                    })
-                },
-            )
-            .ok()
         } else {
-            active_session
+            None
         };
 
+        match transition {
+            Some(transition) => transitions.push((project.id.clone(), transition)),
+            None => {
+                // Heartbeat for close_stale_manual_sessions
+                if active_session.is_some_and(|s| s.paused_at.is_none() && now - s.last_claude_check >= SESSION_HEARTBEAT_MS) {
+                    heartbeats.push(project.id.clone());
+                }
+            }
+        }
+
+        scanned.push((project, claude_sessions, claude_session_count));
+    }
+
+    // Apply every change in one transaction, then re-fetch the affected sessions in one query.
+    // On failure nothing is written and the sessions are reported as they were.
+    if reconcile && (!transitions.is_empty() || !heartbeats.is_empty()) {
+        match apply_session_transitions(&conn, &transitions, &heartbeats, now) {
+            Ok(()) => {
+                // Tell the frontend directly rather than leaving it to diff statuses
+                for (project_id, transition) in &transitions {
+                    let event = match transition {
+                        SessionTransition::Start => Some(("tracking-auto-started", now)),
+                        SessionTransition::Close { end_time, .. } => Some(("tracking-auto-stopped", *end_time)),
+                        SessionTransition::IdlePause { .. } | SessionTransition::IdleResume { .. } => None,
+                    };
+                    if let Some((name, timestamp)) = event {
+                        let _ = app_handle.emit(name, TrackingEvent { project_id: project_id.clone(), timestamp });
+                    }
+                }
+                for (project_id, _) in &transitions {
+                    sessions_map.remove(project_id);
+                }
+                let changed_ids: Vec<&str> = transitions.iter().map(|(id, _)| id.as_str()).collect();
+                for session in load_active_sessions(&conn, &changed_ids)? {
+                    sessions_map.insert(session.project_id.clone(), session);
+                }
+            }
+            Err(e) => eprintln!("Failed to update tracking sessions: {}", e),
+        }
+    }
+
+    // Pass 2: build statuses from the settled sessions
+    let mut project_statuses = Vec::new();
+    let mut today_total: i64 = 0;
+    let mut today_earnings = Cents(0);
+    let mut week_earnings = Cents(0);
+
+    for (project, claude_sessions, claude_session_count) in scanned {
+        // Hooks are source of truth for both display and tracking
+        let claude_state = if claude_session_count > 0 { "active" } else { "stopped" };
+
+        let final_session = sessions_map.get(&project.id);
         let is_tracking = final_session.is_some();
-        let manual_mode = final_session.as_ref().map(|s| s.manual_mode).unwrap_or(false);
-        let elapsed_time = final_session.as_ref().map(|s| now - s.start_time).unwrap_or(0);
+        let is_paused = final_session.map(|s| s.paused_at.is_some()).unwrap_or(false);
+        let manual_mode = final_session.map(|s| s.manual_mode).unwrap_or(false);
+        let elapsed_time = final_session.map(|s| s.elapsed_ms(now)).unwrap_or(0);
+
+        // In-progress sessions aren't in time_entries yet; count Claude ones so the total updates live
+        if final_session.is_some_and(|s| s.claude_code_detected) {
+            claude_total += elapsed_time;
+        }
 
         // Get times from pre-fetched map (default to 0 if no entries)
-        let (today_time, week_time, total_time) = time_map.get(&project.id).copied().unwrap_or((0, 0, 0));
+        let (today_time, week_time, total_time, claude_time) =
+            time_map.get(&project.id).copied().unwrap_or((0, 0, 0, 0));
         today_total += today_time;
 
+        // The running session counts toward today and the goal as it goes, up to the time since
+        // midnight (or Monday) for sessions that started earlier
+        let running_today = final_session.map(|s| s.elapsed_since_ms(today_start, now)).unwrap_or(0);
+        let running_week = final_session.map(|s| s.elapsed_since_ms(week_start, now)).unwrap_or(0);
+        today_total += running_today;
+        let goal_progress = goal_progress(&project, today_time + running_today, week_time + running_week);
+        if let Some(rate) = project.hourly_rate {
+            today_earnings += Cents::for_hours((today_time + running_today) as f64 / 3600000.0, rate);
+            week_earnings += Cents::for_hours((week_time + running_week) as f64 / 3600000.0, rate);
+        }
+
         project_statuses.push(ProjectStatus {
             project,
             is_tracking,
+            is_paused,
             manual_mode,
             elapsed_time,
             today_time,
             week_time,
             total_time,
+            claude_time,
             claude_state: claude_state.to_string(),
             claude_session_count,
+            claude_sessions,
+            goal_progress,
         });
     }
 
@@ -924,441 +2768,3105 @@ fn get_status(state: State<AppState>) -> Result<Status, String> {
         projects: project_statuses,
         today_total,
         claude_total,
+        today_earnings: today_earnings.as_amount(),
+        week_earnings: week_earnings.as_amount(),
+        system_idle_time,
     })
 }
 
+// One project's status for focused views, without recomputing every project or checking idle time.
+// Read-only: auto-tracking transitions are applied by get_status, so sessions are reported as they stand.
 #[tauri::command]
-fn get_entries(project_id: String, day_start: Option<i64>, state: State<AppState>) -> Result<Vec<TimeEntry>, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+fn get_project_status(project_id: String, state: State<AppState>) -> Result<ProjectStatus, ProtimerError> {
+    let cached_entries = {
+        let mut cache = lock_recover(&state.cache, "activity cache");
+        refresh_activity_cache(&mut cache);
+        Arc::clone(&cache.entries)
+    };
 
-    if let Some(start) = day_start {
-        let day_end = start + 86_400_000; // 24 hours in ms
-        let mut stmt = conn
-            .prepare("SELECT id, projectId, startTime, endTime, claudeCodeActive, description FROM time_entries WHERE projectId = ?1 AND startTime >= ?2 AND startTime < ?3 ORDER BY startTime DESC")
-            .map_err(|e| e.to_string())?;
+    let conn = state.readers.get();
+    let now = now_ms();
 
-        let entries: Vec<TimeEntry> = stmt.query_map(params![project_id, start, day_end], |row| {
-            Ok(TimeEntry {
-                id: row.get(0)?,
-                project_id: row.get(1)?,
-                start_time: row.get(2)?,
-                end_time: row.get(3)?,
-                claude_code_active: row.get::<_, i32>(4)? == 1,
-                description: row.get(5)?,
-            })
-        })
-        .map_err(|e| e.to_string())?
+    // Every active project is needed to tell which one owns each Claude session's cwd
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM projects WHERE archived = 0 OR id = ?1", PROJECT_COLUMNS))?;
+    let projects: Vec<Project> = stmt
+        .query_map(params![project_id], project_from_row)?
         .filter_map(|r| r.ok())
         .collect();
+    drop(stmt);
 
-        Ok(entries)
-    } else {
-        let mut stmt = conn
-            .prepare("SELECT id, projectId, startTime, endTime, claudeCodeActive, description FROM time_entries WHERE projectId = ?1 ORDER BY startTime DESC")
-            .map_err(|e| e.to_string())?;
+    let project = projects
+        .iter()
+        .find(|p| p.id == project_id)
+        .cloned()
+        .ok_or_else(|| ProtimerError::NotFound(format!("Project not found: {}", project_id)))?;
 
-        let entries: Vec<TimeEntry> = stmt.query_map(params![project_id], |row| {
-            Ok(TimeEntry {
-                id: row.get(0)?,
-                project_id: row.get(1)?,
-                start_time: row.get(2)?,
-                end_time: row.get(3)?,
-                claude_code_active: row.get::<_, i32>(4)? == 1,
-                description: row.get(5)?,
-            })
-        })
-        .map_err(|e| e.to_string())?
-        .filter_map(|r| r.ok())
-        .collect();
+    let session = get_active_session(&conn, &project_id);
+    let today_start = get_today_start_ms();
+    let week_start = get_week_start_ms();
 
-        Ok(entries)
-    }
-}
+    let (today_time, week_time, total_time, claude_time): (i64, i64, i64, i64) = conn.query_row(
+        "SELECT
+            COALESCE(SUM(CASE WHEN startTime >= ?2 THEN endTime - startTime ELSE 0 END), 0),
+            COALESCE(SUM(CASE WHEN startTime >= ?3 THEN endTime - startTime ELSE 0 END), 0),
+            COALESCE(SUM(endTime - startTime), 0),
+            COALESCE(SUM(CASE WHEN claudeCodeActive = 1 THEN endTime - startTime ELSE 0 END), 0)
+         FROM time_entries
+         WHERE projectId = ?1 AND endTime IS NOT NULL AND deletedAt IS NULL",
+        params![project_id, today_start, week_start],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )?;
 
-#[tauri::command]
-fn get_data_path() -> String {
-    get_data_dir().to_string_lossy().to_string()
-}
+    let session_activity = index_activity_entries(&cached_entries);
+    let cwd_owners = index_cwd_owners(&session_activity, &projects);
+    let claude_sessions = get_claude_sessions_for_project_cached(&project.id, &cwd_owners, &session_activity);
+    let claude_session_count = claude_sessions.iter().filter(|s| s.state == "active").count() as i32;
+    let claude_state = if claude_session_count > 0 { "active" } else { "stopped" };
+    let goal_progress = goal_progress(
+        &project,
+        today_time + session.as_ref().map(|s| s.elapsed_since_ms(today_start, now)).unwrap_or(0),
+        week_time + session.as_ref().map(|s| s.elapsed_since_ms(week_start, now)).unwrap_or(0),
+    );
 
-#[tauri::command]
-fn open_data_folder() -> Result<(), String> {
-    let path = get_data_dir();
-    Command::new("open")
-        .arg(path)
-        .spawn()
-        .map_err(|e| e.to_string())?;
-    Ok(())
+    Ok(ProjectStatus {
+        project,
+        is_tracking: session.is_some(),
+        is_paused: session.as_ref().is_some_and(|s| s.paused_at.is_some()),
+        manual_mode: session.as_ref().is_some_and(|s| s.manual_mode),
+        elapsed_time: session.as_ref().map(|s| s.elapsed_ms(now)).unwrap_or(0),
+        today_time,
+        week_time,
+        total_time,
+        claude_time,
+        claude_state: claude_state.to_string(),
+        claude_session_count,
+        claude_sessions,
+        goal_progress,
+    })
 }
 
+// Progress toward each active project's goals, for projects that have one
 #[tauri::command]
-fn open_invoices_folder() -> Result<(), String> {
-    let invoices_dir = invoice::get_invoices_dir();
-    Command::new("open")
-        .arg(invoices_dir)
-        .spawn()
-        .map_err(|e| e.to_string())?;
-    Ok(())
-}
+fn get_goals_status(state: State<AppState>) -> Result<Vec<GoalStatus>, ProtimerError> {
+    let conn = state.readers.get();
+    let now = now_ms();
+    let today_start = get_today_start_ms();
+    let week_start = get_week_start_ms();
 
-#[tauri::command]
-fn open_file(file_path: String) -> Result<(), String> {
-    Command::new("open")
-        .arg(file_path)
-        .spawn()
-        .map_err(|e| e.to_string())?;
-    Ok(())
-}
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM projects
+         WHERE archived = 0 AND (weeklyGoalMs IS NOT NULL OR dailyGoalMs IS NOT NULL)
+         ORDER BY name",
+        PROJECT_COLUMNS
+    ))?;
+    let projects: Vec<Project> = stmt.query_map([], project_from_row)?.filter_map(|r| r.ok()).collect();
+    drop(stmt);
 
-#[tauri::command]
-fn delete_entry(entry_id: String, state: State<AppState>) -> Result<(), String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
-    conn.execute("DELETE FROM time_entries WHERE id = ?1", params![entry_id])
-        .map_err(|e| e.to_string())?;
-    Ok(())
-}
+    let mut times: std::collections::HashMap<String, (i64, i64)> = std::collections::HashMap::new();
+    {
+        let mut stmt = conn.prepare(
+            "SELECT projectId,
+                COALESCE(SUM(CASE WHEN startTime >= ?1 THEN endTime - startTime ELSE 0 END), 0),
+                COALESCE(SUM(endTime - startTime), 0)
+             FROM time_entries
+             WHERE endTime IS NOT NULL AND deletedAt IS NULL AND startTime >= ?2
+             GROUP BY projectId",
+        )?;
+        let rows = stmt.query_map(params![today_start, week_start], |row| {
+            Ok((row.get::<_, String>(0)?, (row.get::<_, i64>(1)?, row.get::<_, i64>(2)?)))
+        })?;
+        times.extend(rows.filter_map(|r| r.ok()));
+    }
 
-#[tauri::command]
-fn update_entry(entry_id: String, start_time: i64, end_time: i64, state: State<AppState>) -> Result<(), String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
-    conn.execute(
-        "UPDATE time_entries SET startTime = ?1, endTime = ?2 WHERE id = ?3",
-        params![start_time, end_time, entry_id],
-    )
-    .map_err(|e| e.to_string())?;
-    Ok(())
+    let ids: Vec<&str> = projects.iter().map(|p| p.id.as_str()).collect();
+    let sessions: std::collections::HashMap<String, ActiveSession> = load_active_sessions(&conn, &ids)?
+        .into_iter()
+        .map(|s| (s.project_id.clone(), s))
+        .collect();
+
+    let remaining = |goal: Option<i64>, done: i64| goal.map(|goal| (goal - done).max(0));
+    let goals = projects
+        .iter()
+        .map(|project| {
+            let (mut today_ms, mut week_ms) = times.get(&project.id).copied().unwrap_or((0, 0));
+            if let Some(session) = sessions.get(&project.id) {
+                today_ms += session.elapsed_since_ms(today_start, now);
+                week_ms += session.elapsed_since_ms(week_start, now);
+            }
+            GoalStatus {
+                project_id: project.id.clone(),
+                project_name: project.name.clone(),
+                today_ms,
+                week_ms,
+                daily_goal_ms: project.daily_goal_ms,
+                daily_remaining_ms: remaining(project.daily_goal_ms, today_ms),
+                weekly_goal_ms: project.weekly_goal_ms,
+                weekly_remaining_ms: remaining(project.weekly_goal_ms, week_ms),
+                goal_progress: goal_progress(project, today_ms, week_ms),
+            }
+        })
+        .collect();
+
+    Ok(goals)
 }
 
+// Tool counts from the activity log for Claude sessions credited to the project within [start, end], most used first
 #[tauri::command]
-fn add_time_entry(project_id: String, start_time: i64, end_time: i64, state: State<AppState>) -> Result<TimeEntry, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
-
-    let entry = TimeEntry {
-        id: generate_id(),
+fn get_claude_tool_breakdown(
+    project_id: String,
+    start: i64,
+    end: i64,
+    state: State<AppState>,
+) -> Result<Vec<ToolUsage>, ProtimerError> {
+    let cached_entries = {
+        let mut cache = lock_recover(&state.cache, "activity cache");
+        refresh_activity_cache(&mut cache);
+        Arc::clone(&cache.entries)
+    };
+
+    let conn = state.readers.get();
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM projects WHERE archived = 0 OR id = ?1", PROJECT_COLUMNS))?;
+    let projects: Vec<Project> = stmt
+        .query_map(params![project_id], project_from_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+    drop(stmt);
+
+    if !projects.iter().any(|p| p.id == project_id) {
+        return Err(ProtimerError::NotFound(format!("Project not found: {}", project_id)));
+    }
+
+    // Same cwd ownership as auto-tracking, so nested projects don't share tool counts
+    let mut owners: std::collections::HashMap<&str, bool> = std::collections::HashMap::new();
+    let mut counts: std::collections::HashMap<&str, i64> = std::collections::HashMap::new();
+    for entry in cached_entries.iter() {
+        if entry.timestamp < start || entry.timestamp > end {
+            continue;
+        }
+        let (cwd, tool) = match (entry.cwd.as_deref(), entry.tool.as_deref()) {
+            (Some(cwd), Some(tool)) if tool != "none" => (cwd, tool),
+            _ => continue,
+        };
+        let owned = *owners
+            .entry(cwd)
+            .or_insert_with(|| owning_project(cwd, &projects).is_some_and(|p| p.id == project_id));
+        if owned {
+            *counts.entry(tool).or_insert(0) += 1;
+        }
+    }
+
+    let mut breakdown: Vec<ToolUsage> = counts
+        .into_iter()
+        .map(|(tool, count)| ToolUsage { tool: tool.to_string(), count })
+        .collect();
+    breakdown.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tool.cmp(&b.tool)));
+    Ok(breakdown)
+}
+
+// Columns selected for time_entries rows, in the order time_entry_from_row expects
+const TIME_ENTRY_COLUMNS: &str = "id, projectId, startTime, endTime, claudeCodeActive, description, tags, invoiceId";
+
+fn time_entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<TimeEntry> {
+    Ok(TimeEntry {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        start_time: row.get(2)?,
+        end_time: row.get(3)?,
+        claude_code_active: row.get::<_, i32>(4)? == 1,
+        description: row.get(5)?,
+        tags: parse_tags(row.get::<_, Option<String>>(6)?.as_deref()),
+        invoice_id: row.get(7)?,
+    })
+}
+
+// Tags are stored comma-separated; empty or missing means untagged
+fn parse_tags(tags: Option<&str>) -> Vec<String> {
+    tags.unwrap_or("")
+        .split(',')
+        .map(|t| t.trim())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_string())
+        .collect()
+}
+
+#[tauri::command]
+fn get_entries(
+    project_id: String,
+    day_start: Option<i64>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+    state: State<AppState>,
+) -> Result<EntryPage, ProtimerError> {
+    let conn = state.readers.get();
+
+    // Without day_start the whole history is in range
+    let (start, end) = match day_start {
+        Some(start) => (start, start + 86_400_000), // 24 hours in ms
+        None => (i64::MIN, i64::MAX),
+    };
+
+    let total_count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM time_entries WHERE projectId = ?1 AND startTime >= ?2 AND startTime < ?3 AND deletedAt IS NULL",
+        params![project_id, start, end],
+        |row| row.get(0),
+    )?;
+
+    // A negative LIMIT means no limit in SQLite
+    let limit = limit.map(i64::from).unwrap_or(-1);
+    let offset = i64::from(offset.unwrap_or(0));
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM time_entries WHERE projectId = ?1 AND startTime >= ?2 AND startTime < ?3 AND deletedAt IS NULL ORDER BY startTime DESC LIMIT ?4 OFFSET ?5", TIME_ENTRY_COLUMNS))?;
+
+    let entries: Vec<TimeEntry> = stmt.query_map(params![project_id, start, end, limit, offset], time_entry_from_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(EntryPage { entries, total_count })
+}
+
+// Entries whose description contains the query, newest first. SQLite's LIKE is already
+// case-insensitive for ASCII; % and _ in the query are matched literally.
+#[tauri::command]
+fn search_entries(query: String, project_id: Option<String>, state: State<AppState>) -> Result<Vec<TimeEntry>, ProtimerError> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err(ProtimerError::Validation("Search text cannot be empty".to_string()));
+    }
+    let pattern = format!("%{}%", query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+
+    let conn = state.readers.get();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM time_entries
+         WHERE description LIKE ?1 ESCAPE '\\' AND (?2 IS NULL OR projectId = ?2) AND deletedAt IS NULL
+         ORDER BY startTime DESC",
+        TIME_ENTRY_COLUMNS
+    ))?;
+    let entries = stmt
+        .query_map(params![pattern, project_id], time_entry_from_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(entries)
+}
+
+#[tauri::command]
+fn get_schema_version(state: State<AppState>) -> Result<i64, ProtimerError> {
+    let conn = state.readers.get();
+    schema_version(&conn).map_err(ProtimerError::from)
+}
+
+#[tauri::command]
+fn get_data_path() -> Result<String, ProtimerError> {
+    Ok(get_data_dir()?.to_string_lossy().to_string())
+}
+
+// Program that opens a file or folder with its default application
+#[cfg(target_os = "macos")]
+const PLATFORM_OPENER: &str = "open";
+#[cfg(target_os = "windows")]
+const PLATFORM_OPENER: &str = "explorer";
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const PLATFORM_OPENER: &str = "xdg-open";
+
+fn open_with_platform_opener(path: &Path) -> Result<(), ProtimerError> {
+    if !path.exists() {
+        return Err(ProtimerError::NotFound(format!("Not found: {}", path.display())));
+    }
+
+    Command::new(PLATFORM_OPENER)
+        .arg(path)
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => format!("Could not open {}: `{}` is not installed", path.display(), PLATFORM_OPENER),
+            _ => format!("Could not open {}: {}", path.display(), e),
+        })?;
+    Ok(())
+}
+
+#[tauri::command]
+fn open_data_folder() -> Result<(), ProtimerError> {
+    open_with_platform_opener(&get_data_dir()?)
+}
+
+#[tauri::command]
+fn open_invoices_folder() -> Result<(), ProtimerError> {
+    open_with_platform_opener(&invoice::get_invoices_dir()?)
+}
+
+#[tauri::command]
+fn open_file(file_path: String) -> Result<(), ProtimerError> {
+    open_with_platform_opener(Path::new(&file_path))
+}
+
+// Soft delete: the row stays recoverable via restore_entry until purged
+#[tauri::command]
+fn delete_entry(entry_id: String, state: State<AppState>) -> Result<(), ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
+    conn.execute(
+        "UPDATE time_entries SET deletedAt = ?1 WHERE id = ?2 AND deletedAt IS NULL",
+        params![now_ms(), entry_id],
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+fn restore_entry(entry_id: String, state: State<AppState>) -> Result<(), ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
+
+    let (project_id, start_time, end_time): (String, i64, Option<i64>) = conn
+        .query_row(
+            "SELECT projectId, startTime, endTime FROM time_entries WHERE id = ?1 AND deletedAt IS NOT NULL",
+            params![entry_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|e| ProtimerError::NotFound(format!("Deleted entry not found: {}", e)))?;
+
+    // Time may have been re-logged since the delete
+    if let Some(conflict_id) = find_overlapping_entry(&conn, &project_id, start_time, end_time.unwrap_or(start_time), Some(entry_id.as_str()))?
+    {
+        return Err(ProtimerError::Validation(format!("Entry overlaps an existing entry: {}", conflict_id)));
+    }
+
+    conn.execute(
+        "UPDATE time_entries SET deletedAt = NULL WHERE id = ?1",
+        params![entry_id],
+    )?;
+    Ok(())
+}
+
+// Permanently remove entries that were soft-deleted more than older_than_ms ago
+#[tauri::command]
+fn purge_deleted_entries(older_than_ms: i64, state: State<AppState>) -> Result<usize, ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
+    let purged = conn
+        .execute(
+            "DELETE FROM time_entries WHERE deletedAt IS NOT NULL AND deletedAt <= ?1",
+            params![now_ms() - older_than_ms],
+        )?;
+    Ok(purged)
+}
+
+// Find another entry for the project whose [start, end) overlaps the given range.
+// Open entries are treated as running until now.
+fn find_overlapping_entry(
+    conn: &Connection,
+    project_id: &str,
+    start_time: i64,
+    end_time: i64,
+    exclude_id: Option<&str>,
+) -> rusqlite::Result<Option<String>> {
+    conn.query_row(
+        "SELECT id FROM time_entries
+         WHERE projectId = ?1 AND startTime < ?3 AND COALESCE(endTime, ?4) > ?2 AND id != COALESCE(?5, '')
+           AND deletedAt IS NULL
+         ORDER BY startTime ASC LIMIT 1",
+        params![project_id, start_time, end_time, now_ms(), exclude_id],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+#[tauri::command]
+fn update_entry(entry_id: String, start_time: i64, end_time: i64, state: State<AppState>) -> Result<(), ProtimerError> {
+    if end_time <= start_time {
+        return Err(ProtimerError::Validation("end time must be after start time".to_string()));
+    }
+
+    let conn = lock_recover(&state.db, "database");
+
+    // Entries in the trash can't be edited, so restoring one brings back the times it was deleted with
+    let project_id: String = conn
+        .query_row(
+            "SELECT projectId FROM time_entries WHERE id = ?1 AND deletedAt IS NULL",
+            params![entry_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| ProtimerError::NotFound(format!("Entry not found: {}", e)))?;
+
+    // Exclude the entry itself so moving its own bounds doesn't conflict
+    if let Some(conflict_id) = find_overlapping_entry(&conn, &project_id, start_time, end_time, Some(entry_id.as_str()))?
+    {
+        return Err(ProtimerError::Validation(format!("Entry overlaps an existing entry: {}", conflict_id)));
+    }
+
+    conn.execute(
+        "UPDATE time_entries SET startTime = ?1, endTime = ?2 WHERE id = ?3",
+        params![start_time, end_time, entry_id],
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+fn update_entry_description(entry_id: String, description: Option<String>, state: State<AppState>) -> Result<(), ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
+    let updated = conn.execute(
+        "UPDATE time_entries SET description = ?1 WHERE id = ?2 AND deletedAt IS NULL",
+        params![description, entry_id],
+    )?;
+    if updated == 0 {
+        return Err(ProtimerError::NotFound("Entry not found".to_string()));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn set_entry_tags(entry_id: String, tags: Vec<String>, state: State<AppState>) -> Result<(), ProtimerError> {
+    if tags.iter().any(|t| t.contains(',')) {
+        return Err(ProtimerError::Validation("Tags cannot contain commas".to_string()));
+    }
+
+    // Trim and drop blanks/duplicates so stored tags match what get_time_by_tag looks up
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !normalized.iter().any(|t| t == tag) {
+            normalized.push(tag.to_string());
+        }
+    }
+    let stored = if normalized.is_empty() { None } else { Some(normalized.join(",")) };
+
+    let conn = lock_recover(&state.db, "database");
+    let updated = conn
+        .execute(
+            "UPDATE time_entries SET tags = ?1 WHERE id = ?2 AND deletedAt IS NULL",
+            params![stored, entry_id],
+        )?;
+    if updated == 0 {
+        return Err(ProtimerError::NotFound("Entry not found".to_string()));
+    }
+    Ok(())
+}
+
+// Correct whether an entry counts as Claude-assisted time, e.g. a Claude session that was recorded as manual
+#[tauri::command]
+fn set_entry_claude_flag(entry_id: String, claude_active: bool, state: State<AppState>) -> Result<(), ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
+    let updated = conn.execute(
+        "UPDATE time_entries SET claudeCodeActive = ?1 WHERE id = ?2 AND deletedAt IS NULL",
+        params![if claude_active { 1 } else { 0 }, entry_id],
+    )?;
+    if updated == 0 {
+        return Err(ProtimerError::NotFound("Entry not found".to_string()));
+    }
+    Ok(())
+}
+
+// Largest gap between entries that merge_entries still treats as back-to-back
+const MERGE_MAX_GAP_MS: i64 = 5 * 60 * 1000;
+
+// Replace several completed entries of one project with a single entry spanning all of them.
+// Meant for auto-tracking flicker, so the entries must be contiguous or nearly so.
+#[tauri::command]
+fn merge_entries(entry_ids: Vec<String>, state: State<AppState>) -> Result<TimeEntry, ProtimerError> {
+    let mut ids: Vec<String> = Vec::new();
+    for id in entry_ids {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    if ids.len() < 2 {
+        return Err(ProtimerError::Validation("Select at least two entries to merge".to_string()));
+    }
+
+    let mut conn = lock_recover(&state.db, "database");
+
+    let mut entries = Vec::new();
+    for id in &ids {
+        let entry = conn
+            .query_row(
+                &format!("SELECT {} FROM time_entries WHERE id = ?1 AND deletedAt IS NULL", TIME_ENTRY_COLUMNS),
+                params![id],
+                time_entry_from_row,
+            )
+            .map_err(|e| ProtimerError::NotFound(format!("Entry not found: {}", e)))?;
+        if entry.end_time.is_none() {
+            return Err(ProtimerError::Validation("Only completed entries can be merged".to_string()));
+        }
+        entries.push(entry);
+    }
+
+    let project_id = entries[0].project_id.clone();
+    if entries.iter().any(|e| e.project_id != project_id) {
+        return Err(ProtimerError::Validation("Entries must belong to the same project".to_string()));
+    }
+
+    // A merged entry can only be billed once, so it can't combine time from different invoices
+    let invoice_id = entries[0].invoice_id.clone();
+    if entries.iter().any(|e| e.invoice_id != invoice_id) {
+        return Err(ProtimerError::Validation("Entries billed on different invoices can't be merged".to_string()));
+    }
+
+    entries.sort_by_key(|e| e.start_time);
+
+    let mut end_time = entries[0].end_time.unwrap_or(entries[0].start_time);
+    for entry in entries.iter().skip(1) {
+        if entry.start_time - end_time > MERGE_MAX_GAP_MS {
+            return Err(ProtimerError::Validation("Entries are not adjacent; only back-to-back entries can be merged".to_string()));
+        }
+        end_time = end_time.max(entry.end_time.unwrap_or(entry.start_time));
+    }
+
+    let descriptions: Vec<String> = entries
+        .iter()
+        .filter_map(|e| e.description.as_deref())
+        .map(|d| d.trim())
+        .filter(|d| !d.is_empty())
+        .map(|d| d.to_string())
+        .collect();
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in entries.iter().flat_map(|e| e.tags.iter()) {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+
+    let merged = TimeEntry {
+        id: generate_id(),
+        project_id,
+        start_time: entries[0].start_time,
+        end_time: Some(end_time),
+        claude_code_active: entries.iter().any(|e| e.claude_code_active),
+        description: if descriptions.is_empty() { None } else { Some(descriptions.join("; ")) },
+        tags,
+        invoice_id,
+    };
+
+    // Originals are removed and the merged entry inserted together, so a failure leaves both intact
+    let tx = conn.transaction()?;
+    for id in &ids {
+        tx.execute("DELETE FROM time_entries WHERE id = ?1", params![id])?;
+    }
+
+    // Anything left in the merged span would sit inside one of the gaps
+    if let Some(conflict_id) = find_overlapping_entry(&tx, &merged.project_id, merged.start_time, end_time, None)?
+    {
+        return Err(ProtimerError::Validation(format!("Merged entry would overlap an existing entry: {}", conflict_id)));
+    }
+
+    tx.execute(
+        "INSERT INTO time_entries (id, projectId, startTime, endTime, claudeCodeActive, description, tags, invoiceId) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            merged.id,
+            merged.project_id,
+            merged.start_time,
+            merged.end_time,
+            if merged.claude_code_active { 1 } else { 0 },
+            merged.description,
+            if merged.tags.is_empty() { None } else { Some(merged.tags.join(",")) },
+            merged.invoice_id,
+        ],
+    )?;
+    if let Some(invoice_id) = &merged.invoice_id {
+        tx.execute(
+            "INSERT OR IGNORE INTO invoice_entries (invoiceId, entryId) VALUES (?1, ?2)",
+            params![invoice_id, merged.id],
+        )?;
+    }
+    tx.commit()?;
+
+    Ok(merged)
+}
+
+// Replace a completed entry with two entries meeting at split_at_ms, e.g. to bill two tasks separately
+#[tauri::command]
+fn split_entry(entry_id: String, split_at_ms: i64, state: State<AppState>) -> Result<(TimeEntry, TimeEntry), ProtimerError> {
+    let mut conn = lock_recover(&state.db, "database");
+
+    let entry = conn
+        .query_row(
+            &format!("SELECT {} FROM time_entries WHERE id = ?1 AND deletedAt IS NULL", TIME_ENTRY_COLUMNS),
+            params![entry_id],
+            time_entry_from_row,
+        )
+        .map_err(|e| ProtimerError::NotFound(format!("Entry not found: {}", e)))?;
+
+    let end_time = entry.end_time.ok_or("Only completed entries can be split")?;
+    if split_at_ms <= entry.start_time || split_at_ms >= end_time {
+        return Err(ProtimerError::Validation("Split time must fall strictly inside the entry".to_string()));
+    }
+
+    let tags = if entry.tags.is_empty() { None } else { Some(entry.tags.join(",")) };
+    let first = TimeEntry {
+        id: generate_id(),
+        end_time: Some(split_at_ms),
+        ..entry.clone()
+    };
+    let second = TimeEntry {
+        id: generate_id(),
+        start_time: split_at_ms,
+        ..entry
+    };
+
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM time_entries WHERE id = ?1", params![entry_id])?;
+    for part in [&first, &second] {
+        tx.execute(
+            "INSERT INTO time_entries (id, projectId, startTime, endTime, claudeCodeActive, description, tags, invoiceId) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                part.id,
+                part.project_id,
+                part.start_time,
+                part.end_time,
+                if part.claude_code_active { 1 } else { 0 },
+                part.description,
+                tags,
+                part.invoice_id,
+            ],
+        )?;
+        // Both halves stay on the invoice that billed the original
+        if let Some(invoice_id) = &part.invoice_id {
+            tx.execute(
+                "INSERT OR IGNORE INTO invoice_entries (invoiceId, entryId) VALUES (?1, ?2)",
+                params![invoice_id, part.id],
+            )?;
+        }
+    }
+    tx.commit()?;
+
+    Ok((first, second))
+}
+
+// Move completed entries to another project, e.g. work logged against the wrong client.
+// Billed entries stay put, since their invoice belongs to the original project. Returns how many moved.
+#[tauri::command]
+fn reassign_entries(entry_ids: Vec<String>, target_project_id: String, state: State<AppState>) -> Result<u32, ProtimerError> {
+    let mut conn = lock_recover(&state.db, "database");
+
+    let target_exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM projects WHERE id = ?1)",
+        params![target_project_id],
+        |row| row.get(0),
+    )?;
+    if !target_exists {
+        return Err(ProtimerError::NotFound(format!("Project not found: {}", target_project_id)));
+    }
+
+    let tx = conn.transaction()?;
+    let mut moved = 0;
+    for id in &entry_ids {
+        let entry = tx
+            .query_row(
+                &format!("SELECT {} FROM time_entries WHERE id = ?1 AND deletedAt IS NULL", TIME_ENTRY_COLUMNS),
+                params![id],
+                time_entry_from_row,
+            )
+            .map_err(|e| ProtimerError::NotFound(format!("Entry not found: {}", e)))?;
+        if entry.project_id == target_project_id {
+            continue;
+        }
+        let end_time = entry.end_time.ok_or("Only completed entries can be moved")?;
+        if entry.invoice_id.is_some() {
+            return Err(ProtimerError::Validation(format!("Entry {} has been invoiced and can't be moved", id)));
+        }
+        if let Some(conflict_id) = find_overlapping_entry(&tx, &target_project_id, entry.start_time, end_time, None)? {
+            return Err(ProtimerError::Validation(format!("Entry {} would overlap an existing entry: {}", id, conflict_id)));
+        }
+
+        tx.execute(
+            "UPDATE time_entries SET projectId = ?1 WHERE id = ?2",
+            params![target_project_id, id],
+        )?;
+        moved += 1;
+    }
+    tx.commit()?;
+
+    Ok(moved)
+}
+
+// Fold one project into another: every entry (deleted ones included) and invoice moves to the
+// target, then the source is deleted. Its rate history goes with it, so unbilled time is billed
+// at the target's rates. Entries may now overlap; verify_integrity reports any that do.
+// Returns how many entries moved.
+#[tauri::command]
+fn merge_projects(source_id: String, target_id: String, state: State<AppState>) -> Result<u32, ProtimerError> {
+    if source_id == target_id {
+        return Err(ProtimerError::Validation("Choose two different projects to merge".to_string()));
+    }
+
+    let mut conn = lock_recover(&state.db, "database");
+
+    for id in [&source_id, &target_id] {
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM projects WHERE id = ?1)",
+            params![id],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Err(ProtimerError::NotFound(format!("Project not found: {}", id)));
+        }
+    }
+    if get_active_session(&conn, &source_id).is_some() {
+        return Err(ProtimerError::Validation("Stop tracking the project being merged first".to_string()));
+    }
+
+    let tx = conn.transaction()?;
+    let moved = tx.execute(
+        "UPDATE time_entries SET projectId = ?1 WHERE projectId = ?2",
+        params![target_id, source_id],
+    )?;
+    tx.execute(
+        "UPDATE invoices SET projectId = ?1 WHERE projectId = ?2",
+        params![target_id, source_id],
+    )?;
+    tx.execute("DELETE FROM project_rates WHERE projectId = ?1", params![source_id])?;
+    tx.execute("DELETE FROM projects WHERE id = ?1", params![source_id])?;
+    tx.commit()?;
+
+    Ok(moved as u32)
+}
+
+#[tauri::command]
+fn add_time_entry(project_id: String, start_time: i64, end_time: i64, state: State<AppState>) -> Result<TimeEntry, ProtimerError> {
+    if end_time <= start_time {
+        return Err(ProtimerError::Validation("end time must be after start time".to_string()));
+    }
+
+    let conn = lock_recover(&state.db, "database");
+
+    if let Some(conflict_id) = find_overlapping_entry(&conn, &project_id, start_time, end_time, None)?
+    {
+        return Err(ProtimerError::Validation(format!("Entry overlaps an existing entry: {}", conflict_id)));
+    }
+
+    let entry = TimeEntry {
+        id: generate_id(),
         project_id: project_id.clone(),
         start_time,
         end_time: Some(end_time),
         claude_code_active: false,
         description: None,
+        tags: Vec::new(),
+        invoice_id: None,
     };
 
-    conn.execute(
-        "INSERT INTO time_entries (id, projectId, startTime, endTime, claudeCodeActive, description) VALUES (?1, ?2, ?3, ?4, 0, NULL)",
-        params![entry.id, entry.project_id, entry.start_time, entry.end_time],
-    )
-    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO time_entries (id, projectId, startTime, endTime, claudeCodeActive, description) VALUES (?1, ?2, ?3, ?4, 0, NULL)",
+        params![entry.id, entry.project_id, entry.start_time, entry.end_time],
+    )?;
+
+    Ok(entry)
+}
+
+// Split CSV text into records, each with the line it starts on. Quoted fields may hold commas,
+// doubled quotes and line breaks; blank lines are dropped.
+fn parse_csv(content: &str) -> Vec<(usize, Vec<String>)> {
+    let mut records = Vec::new();
+    let mut record: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                if record.iter().any(|f| !f.trim().is_empty()) {
+                    records.push((record_line, std::mem::take(&mut record)));
+                } else {
+                    record.clear();
+                }
+                line += 1;
+                record_line = line;
+            }
+            _ => field.push(c),
+        }
+    }
+    record.push(field);
+    if record.iter().any(|f| !f.trim().is_empty()) {
+        records.push((record_line, record));
+    }
+
+    records
+}
+
+// An imported start or end: epoch milliseconds, RFC 3339 with an offset, or a wall-clock
+// "YYYY-MM-DD HH:MM[:SS]" (or with a 'T') read in the configured zone
+fn parse_import_time(value: &str) -> Result<i64, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("is missing".to_string());
+    }
+    if let Ok(ms) = value.parse::<i64>() {
+        return Ok(ms);
+    }
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.timestamp_millis());
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+        if let Ok(naive) = chrono::NaiveDateTime::parse_from_str(value, format) {
+            return local_datetime(naive)
+                .single()
+                .map(|dt| dt.timestamp_millis())
+                .ok_or_else(|| format!("{} is ambiguous or skipped in the local timezone", value));
+        }
+    }
+    Err(format!("is not a recognized time: {}", value))
+}
+
+// Bulk-load history from another tracker. The CSV needs a header row naming start and end
+// columns ("start"/"end" or "start time"/"end time", any case) and may have a description column.
+// Rows that don't parse, end before they start or overlap an existing entry are skipped and
+// reported; the rest are added in one transaction.
+#[tauri::command]
+fn import_entries_csv(project_id: String, path: String, state: State<AppState>) -> Result<ImportReport, ProtimerError> {
+    let content = fs::read_to_string(&path)
+        .map_err(|e| ProtimerError::Io(format!("Failed to read CSV file: {}", e)))?;
+    let mut records = parse_csv(content.trim_start_matches('\u{feff}')).into_iter();
+
+    let (_, header) = records
+        .next()
+        .ok_or_else(|| ProtimerError::Validation("CSV file is empty".to_string()))?;
+    let column = |names: &[&str]| {
+        header.iter().position(|h| {
+            let h = h.trim().to_ascii_lowercase().replace(['_', ' '], "");
+            names.contains(&h.as_str())
+        })
+    };
+    let (start_col, end_col) = match (column(&["start", "starttime"]), column(&["end", "endtime"])) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return Err(ProtimerError::Validation("CSV header needs start and end columns".to_string())),
+    };
+    let description_col = column(&["description"]);
+
+    let mut conn = lock_recover(&state.db, "database");
+
+    let exists: Option<i32> = conn
+        .query_row("SELECT 1 FROM projects WHERE id = ?1", params![project_id], |row| row.get(0))
+        .optional()?;
+    if exists.is_none() {
+        return Err(ProtimerError::NotFound(format!("Project not found: {}", project_id)));
+    }
+
+    let tx = conn.transaction()?;
+    let mut imported = 0;
+    let mut skipped = Vec::new();
+
+    for (line, fields) in records {
+        let field = |i: usize| fields.get(i).map(String::as_str).unwrap_or("");
+        let start_time = match parse_import_time(field(start_col)) {
+            Ok(time) => time,
+            Err(e) => {
+                skipped.push(SkippedRow { line, reason: format!("start time {}", e) });
+                continue;
+            }
+        };
+        let end_time = match parse_import_time(field(end_col)) {
+            Ok(time) => time,
+            Err(e) => {
+                skipped.push(SkippedRow { line, reason: format!("end time {}", e) });
+                continue;
+            }
+        };
+
+        if end_time <= start_time {
+            skipped.push(SkippedRow { line, reason: "end time must be after start time".to_string() });
+            continue;
+        }
+        // Rows already imported from this file count too, since they're in the same transaction
+        if let Some(conflict_id) = find_overlapping_entry(&tx, &project_id, start_time, end_time, None)? {
+            skipped.push(SkippedRow { line, reason: format!("overlaps an existing entry: {}", conflict_id) });
+            continue;
+        }
+
+        let description = description_col
+            .map(|i| field(i).trim().to_string())
+            .filter(|d| !d.is_empty());
+        tx.execute(
+            "INSERT INTO time_entries (id, projectId, startTime, endTime, claudeCodeActive, description) VALUES (?1, ?2, ?3, ?4, 0, ?5)",
+            params![generate_id(), project_id, start_time, end_time, description],
+        )?;
+        imported += 1;
+    }
+
+    tx.commit()?;
+
+    Ok(ImportReport { imported, skipped })
+}
+
+// Per-project totals and earnings for entries starting within [start, end].
+// Projects with no time are left out unless include_empty, which keeps a zeroed row for every active project.
+fn build_summary(
+    conn: &Connection,
+    start: chrono::DateTime<chrono::FixedOffset>,
+    end: chrono::DateTime<chrono::FixedOffset>,
+    include_empty: bool,
+) -> Result<WeeklySummary, ProtimerError> {
+    let start_ms = start.timestamp_millis();
+    let end_ms = end.timestamp_millis();
+    let now = now_ms();
+
+    // Get projects with hourly rates
+    let mut stmt = conn
+        .prepare("SELECT id, name, hourlyRate, archived FROM projects")?;
+
+    let projects: Vec<(String, String, Option<f64>, bool)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get::<_, i32>(3)? == 1)))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut summary_projects = Vec::new();
+    let mut total_earnings = Cents(0);
+
+    for (project_id, project_name, hourly_rate, archived) in projects {
+        // Open entries count up to now, matching claude_total in get_status
+        let (total_ms, entry_count): (i64, i32) = conn
+            .query_row(
+                "SELECT COALESCE(SUM(COALESCE(endTime, ?4) - startTime), 0), COUNT(*) FROM time_entries WHERE projectId = ?1 AND startTime >= ?2 AND startTime <= ?3 AND deletedAt IS NULL",
+                params![project_id, start_ms, end_ms, now],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap_or((0, 0));
+
+        if total_ms > 0 || (include_empty && !archived) {
+            let total_hours = (total_ms as f64 / 3600000.0 * 100.0).round() / 100.0;
+            let earnings = hourly_rate.map(|rate| Cents::for_hours(total_hours, rate));
+
+            if let Some(e) = earnings {
+                total_earnings += e;
+            }
+
+            summary_projects.push(WeeklySummaryProject {
+                project_id,
+                project_name,
+                total_ms,
+                total_hours,
+                entry_count,
+                hourly_rate,
+                earnings: earnings.map(Cents::as_amount),
+            });
+        }
+    }
+
+    Ok(WeeklySummary {
+        week_start: start.to_rfc3339(),
+        week_end: end.to_rfc3339(),
+        projects: summary_projects,
+        total_earnings: total_earnings.as_amount(),
+    })
+}
+
+#[tauri::command]
+fn get_weekly_summary(include_empty: Option<bool>, state: State<AppState>) -> Result<WeeklySummary, ProtimerError> {
+    let conn = state.readers.get();
+
+    use chrono::{Datelike, Duration};
+    let now = local_now();
+    let day_of_week = now.weekday().num_days_from_sunday();
+    let days_to_last_sunday = if day_of_week == 0 { 7 } else { day_of_week as i64 };
+    let days_to_last_monday = days_to_last_sunday + 6;
+
+    // Midnight can be skipped or repeated by a DST change in the configured zone, so go through
+    // local_day_start_ms; the week ends 1ms before the following Monday starts
+    let last_monday_date = now.date_naive() - Duration::days(days_to_last_monday);
+    let last_monday = to_local(local_day_start_ms(last_monday_date)).ok_or("Invalid week start")?;
+    let last_sunday =
+        to_local(local_day_start_ms(last_monday_date + Duration::days(7)) - 1).ok_or("Invalid week end")?;
+
+    build_summary(&conn, last_monday, last_sunday, include_empty.unwrap_or(false))
+}
+
+// Same shape as the weekly summary; week_start/week_end hold the first and last instant of the month
+#[tauri::command]
+fn get_monthly_summary(year: i32, month: u32, state: State<AppState>) -> Result<WeeklySummary, ProtimerError> {
+    use chrono::{Duration, NaiveDate};
+
+    let first_day = NaiveDate::from_ymd_opt(year, month, 1)
+        .ok_or_else(|| ProtimerError::Validation(format!("Invalid month: {}-{}", year, month)))?;
+
+    // First day of the following month, rolling December over into January
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    let next_first_day = NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .ok_or_else(|| ProtimerError::Validation(format!("Invalid month: {}-{}", next_year, next_month)))?;
+    let last_day = next_first_day - Duration::days(1);
+
+    let month_start = local_datetime(first_day.and_hms_opt(0, 0, 0).unwrap())
+        .earliest()
+        .ok_or("Invalid month start")?;
+    let month_end = local_datetime(last_day.and_hms_milli_opt(23, 59, 59, 999).unwrap())
+        .latest()
+        .ok_or("Invalid month end")?;
+
+    let conn = state.readers.get();
+    build_summary(&conn, month_start, month_end, false)
+}
+
+// Summary for arbitrary millisecond bounds so the frontend can build custom reports
+#[tauri::command]
+fn get_summary_for_range(start_ms: i64, end_ms: i64, state: State<AppState>) -> Result<WeeklySummary, ProtimerError> {
+    if end_ms < start_ms {
+        return Err(ProtimerError::Validation("End of range must not be before its start".to_string()));
+    }
+
+    let range_start = to_local(start_ms).ok_or("Invalid start date")?;
+    let range_end = to_local(end_ms).ok_or("Invalid end date")?;
+
+    let conn = state.readers.get();
+    build_summary(&conn, range_start, range_end, false)
+}
+
+// Time per tag across all projects for entries starting within [start_ms, end_ms].
+// With no tag given, every tag seen in the range is reported.
+#[tauri::command]
+fn get_time_by_tag(tag: Option<String>, start_ms: i64, end_ms: i64, state: State<AppState>) -> Result<Vec<TagTotal>, ProtimerError> {
+    if end_ms < start_ms {
+        return Err(ProtimerError::Validation("End of range must not be before its start".to_string()));
+    }
+
+    let conn = state.readers.get();
+    let now = now_ms();
+
+    // Open entries count up to now, matching build_summary
+    let mut stmt = conn
+        .prepare(
+            "SELECT tags, COALESCE(endTime, ?3) - startTime FROM time_entries
+             WHERE startTime >= ?1 AND startTime <= ?2 AND deletedAt IS NULL AND tags IS NOT NULL AND tags != ''",
+        )?;
+
+    let rows: Vec<(Option<String>, i64)> = stmt
+        .query_map(params![start_ms, end_ms, now], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let wanted = tag.as_deref().map(|t| t.trim());
+    let mut totals: Vec<TagTotal> = Vec::new();
+    for (tags, duration) in rows {
+        for entry_tag in parse_tags(tags.as_deref()) {
+            if wanted.is_some_and(|w| w != entry_tag) {
+                continue;
+            }
+            match totals.iter_mut().find(|t| t.tag == entry_tag) {
+                Some(total) => {
+                    total.total_ms += duration;
+                    total.entry_count += 1;
+                }
+                None => totals.push(TagTotal { tag: entry_tag, total_ms: duration, entry_count: 1 }),
+            }
+        }
+    }
+
+    // A requested tag with no matching entries still gets a zero row
+    if let Some(w) = wanted {
+        if totals.is_empty() {
+            totals.push(TagTotal { tag: w.to_string(), total_ms: 0, entry_count: 0 });
+        }
+    }
+
+    totals.sort_by(|a, b| b.total_ms.cmp(&a.total_ms));
+    Ok(totals)
+}
+
+// ============== REPORTS ==============
+
+// Tracked [start, end) spans overlapping the range, clipped to it. Open entries run up to now.
+fn load_tracked_spans(
+    conn: &Connection,
+    project_id: Option<&str>,
+    start_ms: i64,
+    end_ms: i64,
+) -> Result<Vec<(i64, i64)>, ProtimerError> {
+    let now = now_ms();
+    let mut stmt = conn
+        .prepare(
+            "SELECT startTime, COALESCE(endTime, ?3) FROM time_entries
+             WHERE startTime < ?2 AND COALESCE(endTime, ?3) > ?1 AND deletedAt IS NULL
+               AND (?4 IS NULL OR projectId = ?4)",
+        )?;
+
+    let spans = stmt
+        .query_map(params![start_ms, end_ms, now, project_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        })?
+        .filter_map(|r| r.ok())
+        .map(|(start, end)| (start.max(start_ms), end.min(end_ms)))
+        .filter(|(start, end)| end > start)
+        .collect();
+
+    Ok(spans)
+}
+
+// Millisecond timestamp of local midnight starting the given day
+fn local_day_start_ms(date: chrono::NaiveDate) -> i64 {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+    match local_datetime(midnight).earliest() {
+        Some(dt) => dt.timestamp_millis(),
+        // Midnight skipped by a DST change; the day starts an hour later
+        None => local_datetime(midnight + chrono::Duration::hours(1))
+            .earliest()
+            .map(|dt| dt.timestamp_millis())
+            .unwrap_or_else(|| midnight.and_utc().timestamp_millis()),
+    }
+}
+
+// Tracked time per local calendar day in [start_ms, end_ms], as ("YYYY-MM-DD", ms) pairs.
+// Every day in the range is present in order, with 0 for days without tracked time;
+// entries crossing midnight are split between the days they cover.
+#[tauri::command]
+fn get_daily_totals(
+    project_id: Option<String>,
+    start_ms: i64,
+    end_ms: i64,
+    state: State<AppState>,
+) -> Result<Vec<(String, i64)>, ProtimerError> {
+    if end_ms < start_ms {
+        return Err(ProtimerError::Validation("End of range must not be before its start".to_string()));
+    }
+
+    let first_day = to_local(start_ms).ok_or("Invalid start date")?.date_naive();
+    let last_day = to_local(end_ms).ok_or("Invalid end date")?.date_naive();
+
+    let conn = state.readers.get();
+    let spans = load_tracked_spans(&conn, project_id.as_deref(), start_ms, end_ms)?;
+
+    let mut totals = Vec::new();
+    let mut day = first_day;
+    while day <= last_day {
+        let next_day = day.succ_opt().ok_or("Date out of range")?;
+        let day_start = local_day_start_ms(day);
+        let day_end = local_day_start_ms(next_day);
+
+        let total: i64 = spans
+            .iter()
+            .map(|(start, end)| ((*end).min(day_end) - (*start).max(day_start)).max(0))
+            .sum();
+        totals.push((day.format("%Y-%m-%d").to_string(), total));
+
+        day = next_day;
+    }
+
+    Ok(totals)
+}
+
+// Tracked time in [start_ms, end_ms] bucketed by local hour of day (index 0 = midnight to 1am).
+// Entries are split at each hour boundary they cross, so long entries spread across slots.
+#[tauri::command]
+fn get_hourly_distribution(
+    project_id: Option<String>,
+    start_ms: i64,
+    end_ms: i64,
+    state: State<AppState>,
+) -> Result<[i64; 24], ProtimerError> {
+    if end_ms < start_ms {
+        return Err(ProtimerError::Validation("End of range must not be before its start".to_string()));
+    }
+
+    let conn = state.readers.get();
+    hourly_distribution(&conn, project_id.as_deref(), start_ms, end_ms)
+}
+
+fn hourly_distribution(
+    conn: &Connection,
+    project_id: Option<&str>,
+    start_ms: i64,
+    end_ms: i64,
+) -> Result<[i64; 24], ProtimerError> {
+    use chrono::Timelike;
+
+    let spans = load_tracked_spans(conn, project_id, start_ms, end_ms)?;
+
+    let mut buckets = [0i64; 24];
+    for (start, end) in spans {
+        let mut t = start;
+        while t < end {
+            let local = to_local(t).ok_or("Invalid entry time")?;
+            // Offsets change by whole hours at DST transitions, so the next local hour
+            // always starts where the current one's minutes run out
+            let into_hour = local.minute() as i64 * 60_000 + local.second() as i64 * 1000 + local.timestamp_subsec_millis() as i64;
+            let next_hour = t - into_hour + 3_600_000;
+            let slice_end = next_hour.min(end);
+            buckets[local.hour() as usize] += slice_end - t;
+            t = slice_end;
+        }
+    }
+
+    Ok(buckets)
+}
+
+// ============== SETTINGS ==============
+
+// Keys for app behaviour settings. Values are stored as text; business and invoice details
+// stay in business_info. Any other key is free for the frontend to use.
+const SETTING_IDLE_THRESHOLD_MS: &str = "idleThresholdMs";
+const SETTING_BACKUP_INTERVAL_HOURS: &str = "backupIntervalHours";
+const SETTING_TIMEZONE: &str = "timezone";
+const SETTING_PAUSE_AUTO_ON_IDLE: &str = "pauseAutoOnIdle";
+// Comma-separated #RRGGBB colors for new projects, e.g. a set that reads well in dark mode
+const SETTING_PROJECT_PALETTE: &str = "projectPalette";
+
+// Hours between automatic backups unless changed in Settings
+const DEFAULT_BACKUP_INTERVAL_HOURS: i64 = 24;
+
+fn load_setting(conn: &Connection, key: &str) -> rusqlite::Result<Option<String>> {
+    conn.query_row("SELECT value FROM settings WHERE key = ?1", params![key], |row| row.get(0))
+        .optional()
+}
+
+// The backup interval setting, or the default when it's missing or unreadable
+fn get_backup_interval_hours(conn: &Connection) -> i64 {
+    load_setting(conn, SETTING_BACKUP_INTERVAL_HOURS)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_BACKUP_INTERVAL_HOURS)
+}
+
+fn load_settings(conn: &Connection) -> rusqlite::Result<std::collections::HashMap<String, String>> {
+    let mut stmt = conn.prepare("SELECT key, value FROM settings")?;
+    let settings = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(settings)
+}
+
+// None removes the setting, so readers fall back to the default
+fn store_setting(conn: &Connection, key: &str, value: Option<&str>) -> rusqlite::Result<()> {
+    match value {
+        Some(value) => conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?,
+        None => conn.execute("DELETE FROM settings WHERE key = ?1", params![key])?,
+    };
+    Ok(())
+}
+
+// Check and normalize a value for one of the known keys; None means remove the setting.
+// Unknown keys are stored as given.
+fn normalize_setting(key: &str, value: &str) -> Result<Option<String>, ProtimerError> {
+    let value = value.trim();
+    match key {
+        SETTING_IDLE_THRESHOLD_MS | SETTING_BACKUP_INTERVAL_HOURS => {
+            let number: i64 = value
+                .parse()
+                .map_err(|_| ProtimerError::Validation(format!("{} must be a whole number: {}", key, value)))?;
+            Ok(Some(number.max(0).to_string()))
+        }
+        // An empty string clears the override; anything else must be an IANA zone name
+        SETTING_TIMEZONE => {
+            if value.is_empty() {
+                Ok(None)
+            } else if value.parse::<chrono_tz::Tz>().is_err() {
+                Err(ProtimerError::Validation(format!("Unknown timezone: {}", value)))
+            } else {
+                Ok(Some(value.to_string()))
+            }
+        }
+        SETTING_PAUSE_AUTO_ON_IDLE => match value {
+            "true" | "false" => Ok(Some(value.to_string())),
+            _ => Err(ProtimerError::Validation(format!("{} must be true or false: {}", key, value))),
+        },
+        // An empty palette goes back to the default one
+        SETTING_PROJECT_PALETTE => {
+            let palette = parse_palette(value)?;
+            Ok(if palette.is_empty() { None } else { Some(palette.join(",")) })
+        }
+        _ => Ok(Some(value.to_string())),
+    }
+}
+
+#[tauri::command]
+fn get_setting(key: String, state: State<AppState>) -> Result<Option<String>, ProtimerError> {
+    let conn = state.readers.get();
+    load_setting(&conn, &key).map_err(ProtimerError::from)
+}
+
+#[tauri::command]
+fn get_all_settings(state: State<AppState>) -> Result<std::collections::HashMap<String, String>, ProtimerError> {
+    let conn = state.readers.get();
+    load_settings(&conn).map_err(ProtimerError::from)
+}
+
+#[tauri::command]
+fn set_setting(key: String, value: String, state: State<AppState>) -> Result<(), ProtimerError> {
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(ProtimerError::Validation("Setting key cannot be empty".to_string()));
+    }
+    let value = normalize_setting(key, &value)?;
+
+    let conn = lock_recover(&state.db, "database");
+    store_setting(&conn, key, value.as_deref())?;
+    if key == SETTING_TIMEZONE {
+        refresh_timezone_override(&conn);
+    }
+
+    Ok(())
+}
+
+// ============== BUSINESS INFO & INVOICE COMMANDS ==============
+
+// Business details from business_info, plus the app behaviour settings the frontend still
+// reads and writes through BusinessInfo
+fn load_business_info(conn: &Connection) -> rusqlite::Result<BusinessInfo> {
+    let settings = load_settings(conn)?;
+    // Settings that don't parse fall back to their defaults rather than failing every load
+    let setting = |key: &str| settings.get(key).map(String::as_str);
+
+    conn.query_row(
+        "SELECT name, email, taxRate, currency, invoiceNumbering, invoicePrefix, logoPath, fontPath,
+            notes, paymentTerms, paymentDueDays, taxInclusive, taxes, pageSize FROM business_info WHERE id = 1",
+        [],
+        |row| {
+            let email: String = row.get(1)?;
+            Ok(BusinessInfo {
+                name: row.get(0)?,
+                email: if email.is_empty() { None } else { Some(email) },
+                tax_rate: row.get(2)?,
+                currency: row.get(3)?,
+                idle_threshold_ms: setting(SETTING_IDLE_THRESHOLD_MS)
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_IDLE_THRESHOLD_MS),
+                invoice_numbering: row.get(4)?,
+                invoice_prefix: row.get(5)?,
+                logo_path: row.get::<_, Option<String>>(6)?.filter(|p| !p.is_empty()),
+                font_path: row.get::<_, Option<String>>(7)?.filter(|p| !p.is_empty()),
+                notes: row.get::<_, Option<String>>(8)?.filter(|n| !n.trim().is_empty()),
+                payment_terms: row.get::<_, Option<String>>(9)?.filter(|t| !t.trim().is_empty()),
+                payment_due_days: row.get(10)?,
+                backup_interval_hours: get_backup_interval_hours(conn),
+                timezone: setting(SETTING_TIMEZONE).filter(|t| !t.is_empty()).map(str::to_string),
+                pause_auto_on_idle: setting(SETTING_PAUSE_AUTO_ON_IDLE) == Some("true"),
+                tax_inclusive: row.get::<_, i32>(11)? == 1,
+                // Unparseable JSON falls back to the single tax rate rather than failing every load
+                taxes: row
+                    .get::<_, Option<String>>(12)?
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                page_size: row.get(13)?,
+            })
+        },
+    )
+}
+
+#[tauri::command]
+fn get_business_info(state: State<AppState>) -> Result<BusinessInfo, ProtimerError> {
+    let conn = state.readers.get();
+    load_business_info(&conn).map_err(ProtimerError::from)
+}
+
+#[tauri::command]
+fn save_business_info(
+    name: String,
+    email: Option<String>,
+    tax_rate: f64,
+    currency: Option<String>,
+    idle_threshold_ms: Option<i64>,
+    invoice_numbering: Option<String>,
+    invoice_prefix: Option<String>,
+    logo_path: Option<String>,
+    font_path: Option<String>,
+    notes: Option<String>,
+    payment_terms: Option<String>,
+    payment_due_days: Option<i64>,
+    backup_interval_hours: Option<i64>,
+    timezone: Option<String>,
+    pause_auto_on_idle: Option<bool>,
+    tax_inclusive: Option<bool>,
+    taxes: Option<Vec<TaxSetting>>,
+    page_size: Option<String>,
+    state: State<AppState>,
+) -> Result<(), ProtimerError> {
+    if let Some(ref mode) = invoice_numbering {
+        if mode != "dateRange" && mode != "sequential" {
+            return Err(ProtimerError::Validation(format!("Invalid invoice numbering mode: {}", mode)));
+        }
+    }
+
+    if let Some(ref size) = page_size {
+        if invoice::PageSize::from_name(size).is_none() {
+            return Err(ProtimerError::Validation(format!("Invalid page size: {}", size)));
+        }
+    }
+
+    // ISO 4217 codes only; None keeps the current currency
+    let currency = currency.map(|c| c.trim().to_uppercase());
+    if let Some(ref code) = currency {
+        if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(ProtimerError::Validation(format!("Invalid currency code: {}", code)));
+        }
+    }
+
+    // Settings that were given, normalized as set_setting would; a None value removes the setting
+    let mut settings: Vec<(&str, Option<String>)> = Vec::new();
+    if let Some(ms) = idle_threshold_ms {
+        settings.push((SETTING_IDLE_THRESHOLD_MS, normalize_setting(SETTING_IDLE_THRESHOLD_MS, &ms.to_string())?));
+    }
+    if let Some(hours) = backup_interval_hours {
+        settings.push((SETTING_BACKUP_INTERVAL_HOURS, normalize_setting(SETTING_BACKUP_INTERVAL_HOURS, &hours.to_string())?));
+    }
+    if let Some(ref name) = timezone {
+        settings.push((SETTING_TIMEZONE, normalize_setting(SETTING_TIMEZONE, name)?));
+    }
+    if let Some(pause) = pause_auto_on_idle {
+        settings.push((SETTING_PAUSE_AUTO_ON_IDLE, Some(pause.to_string())));
+    }
+
+    // An empty list goes back to the single tax rate
+    let taxes = match taxes {
+        Some(taxes) => {
+            for tax in &taxes {
+                if tax.name.trim().is_empty() {
+                    return Err(ProtimerError::Validation("Each tax needs a name".to_string()));
+                }
+                if !tax.rate.is_finite() || tax.rate < 0.0 {
+                    return Err(ProtimerError::Validation(format!("Invalid rate for {}: {}", tax.name, tax.rate)));
+                }
+            }
+            let taxes: Vec<TaxSetting> = taxes
+                .into_iter()
+                .map(|tax| TaxSetting { name: tax.name.trim().to_string(), rate: tax.rate })
+                .collect();
+            Some(serde_json::to_string(&taxes)?)
+        }
+        None => None,
+    };
+
+    let mut conn = lock_recover(&state.db, "database");
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "UPDATE business_info SET name = ?1, email = ?2, taxRate = ?3, currency = COALESCE(?4, currency),
+            invoiceNumbering = COALESCE(?5, invoiceNumbering),
+            invoicePrefix = COALESCE(?6, invoicePrefix), logoPath = COALESCE(?7, logoPath),
+            fontPath = COALESCE(?8, fontPath), notes = COALESCE(?9, notes), paymentTerms = COALESCE(?10, paymentTerms),
+            paymentDueDays = COALESCE(?11, paymentDueDays), taxInclusive = COALESCE(?12, taxInclusive),
+            taxes = COALESCE(?13, taxes), pageSize = COALESCE(?14, pageSize) WHERE id = 1",
+        params![
+            name,
+            email.unwrap_or_default(),
+            tax_rate,
+            currency,
+            invoice_numbering,
+            invoice_prefix.map(|p| p.trim().to_string()),
+            // An empty string clears the logo or font
+            logo_path.map(|p| p.trim().to_string()),
+            font_path.map(|p| p.trim().to_string()),
+            notes,
+            payment_terms,
+            payment_due_days.map(|days| days.max(0)),
+            tax_inclusive,
+            taxes,
+            page_size,
+        ],
+    )?;
+    for (key, value) in &settings {
+        store_setting(&tx, key, value.as_deref())?;
+    }
+    tx.commit()?;
+
+    refresh_timezone_override(&conn);
+
+    Ok(())
+}
+
+// Taxes to apply on invoices. Settings from before named taxes only have taxRate,
+// which becomes a single "Tax" line.
+fn invoice_taxes(business: &BusinessInfo) -> Vec<TaxSetting> {
+    if !business.taxes.is_empty() {
+        business.taxes.clone()
+    } else if business.tax_rate > 0.0 {
+        vec![TaxSetting { name: "Tax".to_string(), rate: business.tax_rate }]
+    } else {
+        Vec::new()
+    }
+}
+
+// Exclusive taxes are each a percentage of the subtotal. Inclusive taxes back the combined
+// rate out of the gross subtotal and split that by rate, with the rounding remainder on the
+// last line so the lines still add up to gross - net.
+fn compute_tax_lines(subtotal: Cents, taxes: &[TaxSetting], inclusive: bool) -> Vec<invoice::TaxLine> {
+    let combined_rate: f64 = taxes.iter().map(|t| t.rate).sum();
+    let included = if inclusive && combined_rate > 0.0 { subtotal.included_percent(combined_rate) } else { Cents(0) };
+    let mut allocated = Cents(0);
+
+    taxes
+        .iter()
+        .enumerate()
+        .map(|(i, tax)| {
+            let amount = if !inclusive {
+                subtotal.percent(tax.rate)
+            } else if i + 1 == taxes.len() {
+                included - allocated
+            } else {
+                included.percent(tax.rate / combined_rate * 100.0)
+            };
+            allocated += amount;
+            invoice::TaxLine { name: tax.name.clone(), rate: tax.rate, amount }
+        })
+        .collect()
+}
+
+// Round an entry's duration to a multiple of the billing increment in the given direction
+fn billable_duration_ms(duration_ms: i64, increment_minutes: Option<u32>, rounding_mode: invoice::RoundingMode) -> i64 {
+    match increment_minutes {
+        Some(minutes) if minutes > 0 => {
+            let increment_ms = minutes as i64 * 60_000;
+            let bias = match rounding_mode {
+                invoice::RoundingMode::Up => increment_ms - 1,
+                invoice::RoundingMode::Nearest => increment_ms / 2,
+                invoice::RoundingMode::Down => 0,
+            };
+            (duration_ms + bias).div_euclid(increment_ms) * increment_ms
+        }
+        _ => duration_ms,
+    }
+}
+
+// (label, signed amount) pairs: negative is a discount, positive a flat fee
+fn parse_adjustments(adjustments: Option<Vec<(String, f64)>>) -> Result<Vec<invoice::InvoiceAdjustment>, ProtimerError> {
+    adjustments
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(label, amount)| {
+            let label = label.trim().to_string();
+            if label.is_empty() {
+                return Err(ProtimerError::Validation("Each adjustment needs a label".to_string()));
+            }
+            if !amount.is_finite() {
+                return Err(ProtimerError::Validation(format!("Invalid amount for {}: {}", label, amount)));
+            }
+            Ok(invoice::InvoiceAdjustment { label, amount: Cents::from_amount(amount) })
+        })
+        .collect()
+}
+
+// Hours worked outside ProTimer; anything else would slip past the "nothing to invoice" check
+// and issue an empty invoice
+fn validate_extra_hours(extra_hours: f64) -> Result<(), ProtimerError> {
+    if !extra_hours.is_finite() || extra_hours < 0.0 {
+        return Err(ProtimerError::Validation(format!("Extra hours must be zero or more: {}", extra_hours)));
+    }
+    Ok(())
+}
+
+// Project fields an invoice needs; blank client fields are None
+struct InvoiceProject {
+    id: String,
+    name: String,
+    hourly_rate: Option<f64>,
+    client_name: Option<String>,
+    client_email: Option<String>,
+    client_address: Option<String>,
+    invoice_header_note: Option<String>,
+}
+
+fn load_invoice_project(conn: &Connection, project_id: &str) -> Result<InvoiceProject, ProtimerError> {
+    conn.query_row(
+        "SELECT name, hourlyRate, clientName, clientEmail, clientAddress, invoiceHeaderNote FROM projects WHERE id = ?1",
+        params![project_id],
+        |row| {
+            Ok(InvoiceProject {
+                id: project_id.to_string(),
+                name: row.get(0)?,
+                hourly_rate: row.get(1)?,
+                client_name: row.get::<_, Option<String>>(2)?.filter(|n| !n.is_empty()),
+                client_email: row.get::<_, Option<String>>(3)?.filter(|e| !e.is_empty()),
+                client_address: row.get::<_, Option<String>>(4)?.filter(|a| !a.is_empty()),
+                invoice_header_note: row.get::<_, Option<String>>(5)?.filter(|n| !n.is_empty()),
+            })
+        },
+    )
+    .map_err(ProtimerError::from)
+}
+
+// Header notes of the invoiced projects, each once and in order, for the "Ref:" line
+fn invoice_header_note(projects: &[InvoiceProject]) -> Option<String> {
+    let mut notes: Vec<&str> = Vec::new();
+    for note in projects.iter().filter_map(|p| p.invoice_header_note.as_deref()) {
+        if !notes.contains(&note) {
+            notes.push(note);
+        }
+    }
+    if notes.is_empty() {
+        None
+    } else {
+        Some(notes.join("; "))
+    }
+}
+
+// Guard against double-billing: refuse periods already covered by one of the project's invoices
+fn check_invoice_overlap(conn: &Connection, project: &InvoiceProject, start_date: i64, end_date: i64) -> Result<(), ProtimerError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT invoiceNumber FROM invoices
+             WHERE projectId = ?1 AND startDate <= ?3 AND endDate >= ?2
+             ORDER BY startDate ASC",
+        )?;
+    let conflicts: Vec<String> = stmt
+        .query_map(params![project.id, start_date, end_date], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+    if !conflicts.is_empty() {
+        return Err(ProtimerError::Validation(format!(
+            "This period overlaps existing invoice(s) for {}: {}",
+            project.name,
+            conflicts.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+// Which of a project's time entries an invoice bills
+enum BilledEntries<'a> {
+    // Entries starting within the billed period; only_unbilled leaves out those already on an invoice
+    Period { only_unbilled: bool },
+    // Entries linked to an existing invoices row, for regenerating its PDF
+    Linked(&'a str),
+}
+
+// The project's rate for work at `time`, or an error naming the project and the day
+fn project_rate_at(rate_periods: &[(i64, Option<f64>)], project: &InvoiceProject, time: i64) -> Result<f64, ProtimerError> {
+    rate_at(rate_periods, time, project.hourly_rate).ok_or_else(|| {
+        let date = to_local(time).map(|d| d.format("%b %d, %Y").to_string()).unwrap_or_default();
+        ProtimerError::Validation(format!("{} has no hourly rate set for work on {}", project.name, date))
+    })
+}
+
+// Extra hours on a combined invoice, as a row of their own rather than folded into a project's
+// rows. They're billed at the first project's rate at the end of the period.
+fn extra_hours_row(
+    conn: &Connection,
+    project: &InvoiceProject,
+    start_date: i64,
+    end_date: i64,
+    extra_hours: f64,
+) -> Result<invoice::InvoiceEntry, ProtimerError> {
+    let rate = project_rate_at(&load_rate_periods(conn, &project.id)?, project, end_date)?;
+    let start_date_obj = to_local(start_date).ok_or("Invalid start date")?;
+    let end_date_obj = to_local(end_date).ok_or("Invalid end date")?;
+    let hours = (extra_hours * 100.0).round() / 100.0;
+    Ok(invoice::InvoiceEntry {
+        date: format!("{} - {}", start_date_obj.format("%b %d, %Y"), end_date_obj.format("%b %d, %Y")),
+        description: None,
+        hours,
+        rate,
+        amount: Cents::for_hours(hours, rate),
+    })
+}
+
+// One project's billable rows for [start_date, end_date] plus any extra hours, and the ids of
+// the time entries they cover. Itemized invoices get a row per entry; otherwise hours are totalled per rate.
+fn build_invoice_rows(
+    conn: &Connection,
+    project: &InvoiceProject,
+    start_date: i64,
+    end_date: i64,
+    extra_hours: f64,
+    itemize: bool,
+    billing_increment_minutes: Option<u32>,
+    rounding_mode: invoice::RoundingMode,
+    billed_entries: BilledEntries,
+) -> Result<(Vec<invoice::InvoiceEntry>, Vec<String>), ProtimerError> {
+    // Each entry is billed at the rate in effect when the work was done
+    let rate_periods = load_rate_periods(conn, &project.id)?;
+    let rate_for = |time: i64| project_rate_at(&rate_periods, project, time);
+
+    let entry_from_row = |row: &rusqlite::Row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, Option<i64>>(1)?,
+            row.get::<_, Option<String>>(2)?,
+            row.get::<_, String>(3)?,
+        ))
+    };
+    let entries_data = match billed_entries {
+        BilledEntries::Period { only_unbilled } => {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT startTime, endTime, description, id FROM time_entries
+                 WHERE projectId = ?1 AND startTime >= ?2 AND startTime <= ?3 AND deletedAt IS NULL{}
+                 ORDER BY startTime ASC",
+                if only_unbilled { " AND invoiceId IS NULL" } else { "" }
+            ))?;
+            let entries = stmt
+                .query_map(params![project.id, start_date, end_date], entry_from_row)?
+                .filter_map(|r| r.ok())
+                .collect::<Vec<_>>();
+            entries
+        }
+        BilledEntries::Linked(invoice_id) => {
+            // Entries deleted since are still part of what was billed, so they stay on the invoice
+            let mut stmt = conn.prepare(
+                "SELECT startTime, endTime, description, id FROM time_entries
+                 WHERE id IN (SELECT entryId FROM invoice_entries WHERE invoiceId = ?1)
+                 ORDER BY startTime ASC",
+            )?;
+            let entries = stmt
+                .query_map(params![invoice_id], entry_from_row)?
+                .filter_map(|r| r.ok())
+                .collect::<Vec<_>>();
+            entries
+        }
+    };
+
+    // Format date range for the invoice entry
+    let start_date_obj = to_local(start_date).ok_or("Invalid start date")?;
+    let end_date_obj = to_local(end_date).ok_or("Invalid end date")?;
+
+    let date_range = format!(
+        "{} - {}",
+        start_date_obj.format("%b %d, %Y"),
+        end_date_obj.format("%b %d, %Y")
+    );
+
+    let rows = if itemize {
+        // One row per time entry, each rounded on its own so rows sum to the subtotal
+        let mut rows = Vec::new();
+        for (start_time, end_time, description, _id) in &entries_data {
+            let duration_ms = billable_duration_ms(end_time.unwrap_or(*start_time) - start_time, billing_increment_minutes, rounding_mode);
+            let hours = (duration_ms as f64 / 3600000.0 * 100.0).round() / 100.0;
+            let entry_date = to_local(*start_time).ok_or("Invalid entry date")?;
+            let rate = rate_for(*start_time)?;
+            rows.push(invoice::InvoiceEntry {
+                date: entry_date.format("%b %d, %Y").to_string(),
+                description: description.clone().filter(|d| !d.is_empty()),
+                hours,
+                rate,
+                amount: Cents::for_hours(hours, rate),
+            });
+        }
+
+        // Extra hours tracked outside of ProTimer get their own row
+        if extra_hours > 0.0 {
+            let hours = (extra_hours * 100.0).round() / 100.0;
+            let rate = rate_for(end_date)?;
+            rows.push(invoice::InvoiceEntry {
+                date: end_date_obj.format("%b %d, %Y").to_string(),
+                description: Some("Additional hours".to_string()),
+                hours,
+                rate,
+                amount: Cents::for_hours(hours, rate),
+            });
+        }
+
+        rows
+    } else {
+        // Total hours per rate, in the order the rates first apply; one row unless the rate
+        // changed during the period
+        let mut hours_by_rate: Vec<(f64, f64)> = Vec::new();
+        let mut add_hours = |rate: f64, hours: f64| match hours_by_rate.iter_mut().find(|(r, _)| *r == rate) {
+            Some((_, total)) => *total += hours,
+            None => hours_by_rate.push((rate, hours)),
+        };
+
+        // Rounding is applied per entry, not on the total, to match how clients audit invoices
+        for (start_time, end_time, _description, _id) in &entries_data {
+            let duration_ms = billable_duration_ms(end_time.unwrap_or(*start_time) - start_time, billing_increment_minutes, rounding_mode);
+            add_hours(rate_for(*start_time)?, duration_ms as f64 / 3600000.0);
+        }
+
+        // Add extra hours tracked outside of ProTimer
+        if extra_hours > 0.0 {
+            add_hours(rate_for(end_date)?, extra_hours);
+        }
+
+        hours_by_rate
+            .into_iter()
+            .map(|(rate, hours)| {
+                // Round to 2 decimal places
+                let hours = (hours * 100.0).round() / 100.0;
+                invoice::InvoiceEntry {
+                    date: date_range.clone(),
+                    description: None,
+                    hours,
+                    rate,
+                    amount: Cents::for_hours(hours, rate),
+                }
+            })
+            .collect()
+    };
+
+    Ok((rows, entries_data.into_iter().map(|(_, _, _, id)| id).collect()))
+}
+
+// Everything issue_invoice needs beyond the business settings and the billed period
+struct InvoiceDraft {
+    client_name: String,
+    client_email: Option<String>,
+    client_address: Option<String>,
+    project_name: String,
+    header_note: Option<String>,
+    // Folder under the invoices dir the PDF is filed in
+    folder_name: String,
+    // Rows per project, in display order
+    sections: Vec<DraftSection>,
+    adjustments: Vec<invoice::InvoiceAdjustment>,
+    itemize: bool,
+    billing_increment_minutes: Option<u32>,
+    rounding_mode: invoice::RoundingMode,
+    // Resolved from the business settings when issuing, or the snapshot when regenerating
+    taxes: Vec<TaxSetting>,
+    tax_inclusive: bool,
+}
+
+// One project's part of a draft invoice
+struct DraftSection {
+    project_id: String,
+    // Time entries billed by these rows, linked to the invoice in invoice_entries
+    entry_ids: Vec<String>,
+    // Hours tracked outside ProTimer billed with this project
+    extra_hours: f64,
+    section: invoice::InvoiceSection,
+    // Combined invoices show extra hours on their own line, after every project's rows,
+    // instead of in this section
+    extra_row: Option<invoice::InvoiceEntry>,
+}
+
+// How an invoice was built, stored as JSON on each of its invoices rows so
+// regenerate_invoice can rebuild the same rows with the same taxes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InvoiceOptions {
+    itemize: bool,
+    billing_increment_minutes: Option<u32>,
+    rounding_mode: invoice::RoundingMode,
+    // This project's share; extra hours on a combined invoice are recorded on its first
+    // project and shown on a line of their own
+    extra_hours: f64,
+    adjustments: Vec<(String, f64)>,
+    taxes: Vec<TaxSetting>,
+    tax_inclusive: bool,
+    // Rows are grouped under project headings
+    combined: bool,
+}
+
+// A rendered invoice: what to report back, and what to record per project
+struct RenderedInvoice {
+    result: InvoiceResult,
+    projects: Vec<RenderedProject>,
+}
+
+struct RenderedProject {
+    project_id: String,
+    entry_ids: Vec<String>,
+    // Share of the invoice total, by section subtotal
+    total: Cents,
+    options: InvoiceOptions,
+}
+
+// Totals a draft and writes its PDF to output_path. Dates and the PDF metadata come from
+// created_at rather than the clock, so rendering the same draft again gives the same document.
+fn render_invoice(
+    business: BusinessInfo,
+    draft: InvoiceDraft,
+    invoice_number: &str,
+    created_at: i64,
+    output_path: PathBuf,
+) -> Result<RenderedInvoice, ProtimerError> {
+    // Summed in whole cents, so the total always equals the rendered rows plus tax.
+    // Adjustments are rows too, so tax is computed on the adjusted subtotal.
+    let section_totals: Vec<Cents> = draft
+        .sections
+        .iter()
+        .map(|draft_section| draft_section.section.entries.iter().chain(&draft_section.extra_row).map(|e| e.amount).sum())
+        .collect();
+    let subtotal: Cents = section_totals.iter().copied().chain(draft.adjustments.iter().map(|a| a.amount)).sum();
+    if subtotal < Cents(0) {
+        return Err(ProtimerError::Validation("Adjustments bring the invoice subtotal below zero".to_string()));
+    }
+    let taxes = compute_tax_lines(subtotal, &draft.taxes, draft.tax_inclusive);
+    let tax_amount: Cents = taxes.iter().map(|t| t.amount).sum();
+    let total = if draft.tax_inclusive { subtotal } else { subtotal + tax_amount };
+    let total_hours = (draft
+        .sections
+        .iter()
+        .flat_map(|draft_section| draft_section.section.entries.iter().chain(&draft_section.extra_row))
+        .map(|e| e.hours)
+        .sum::<f64>()
+        * 100.0)
+        .round()
+        / 100.0;
+
+    // Split the total across projects by their section subtotals; the last takes the rounding remainder
+    let rows_total: Cents = section_totals.iter().copied().sum();
+    let mut allocated = Cents(0);
+    let project_totals: Vec<Cents> = section_totals
+        .iter()
+        .enumerate()
+        .map(|(i, section_total)| {
+            let share = if i + 1 == section_totals.len() || rows_total.0 == 0 {
+                total - allocated
+            } else {
+                Cents((total.0 as f64 * section_total.0 as f64 / rows_total.0 as f64).round() as i64)
+            };
+            allocated += share;
+            share
+        })
+        .collect();
+
+    // Create invoice data
+    let issued = to_local(created_at).ok_or("Invalid invoice date")?;
+    let invoice_date = issued.format("%Y-%m-%d").to_string();
+    let due_date = if business.payment_due_days > 0 {
+        Some((issued + chrono::Duration::days(business.payment_due_days)).format("%Y-%m-%d").to_string())
+    } else {
+        None
+    };
+
+    let combined = draft.sections.iter().any(|s| s.section.title.is_some());
+    let adjustments: Vec<(String, f64)> = draft.adjustments.iter().map(|a| (a.label.clone(), a.amount.as_amount())).collect();
+    let entry_count: usize = draft.sections.iter().map(|s| s.entry_ids.len()).sum();
+    let mut projects = Vec::with_capacity(draft.sections.len());
+    let mut sections = Vec::with_capacity(draft.sections.len());
+    let mut extra_rows = Vec::new();
+    for (draft_section, project_total) in draft.sections.into_iter().zip(project_totals) {
+        projects.push(RenderedProject {
+            project_id: draft_section.project_id,
+            entry_ids: draft_section.entry_ids,
+            total: project_total,
+            options: InvoiceOptions {
+                itemize: draft.itemize,
+                billing_increment_minutes: draft.billing_increment_minutes,
+                rounding_mode: draft.rounding_mode,
+                extra_hours: draft_section.extra_hours,
+                adjustments: adjustments.clone(),
+                taxes: draft.taxes.clone(),
+                tax_inclusive: draft.tax_inclusive,
+                combined,
+            },
+        });
+        // A project billed only extra hours has no rows of its own to show
+        if !draft_section.section.entries.is_empty() {
+            sections.push(draft_section.section);
+        }
+        extra_rows.extend(draft_section.extra_row);
+    }
+    if !extra_rows.is_empty() {
+        sections.push(invoice::InvoiceSection { title: Some("Additional hours".to_string()), entries: extra_rows });
+    }
+
+    let invoice_data = invoice::InvoiceData {
+        invoice_number: invoice_number.to_string(),
+        invoice_date,
+        business_name: business.name,
+        business_email: business.email,
+        logo_path: business.logo_path,
+        font_path: business.font_path,
+        notes: business.notes,
+        payment_terms: business.payment_terms,
+        page_size: invoice::PageSize::from_name(&business.page_size).unwrap_or(invoice::PageSize::A4),
+        due_date,
+        currency: business.currency,
+        client_name: draft.client_name,
+        client_email: draft.client_email,
+        client_address: draft.client_address,
+        project_name: draft.project_name,
+        header_note: draft.header_note,
+        sections,
+        adjustments: draft.adjustments,
+        billing_increment_minutes: draft.billing_increment_minutes.filter(|m| *m > 0),
+        rounding_mode: draft.rounding_mode,
+        subtotal,
+        taxes,
+        tax_inclusive: draft.tax_inclusive,
+        total,
+        created_at,
+        document_id: invoice_number.to_string(),
+    };
+
+    let pdf_path = invoice::generate_invoice_pdf(invoice_data, output_path).map_err(ProtimerError::PdfGeneration)?;
+
+    Ok(RenderedInvoice {
+        result: InvoiceResult {
+            invoice_number: invoice_number.to_string(),
+            file_path: pdf_path,
+            subtotal: subtotal.as_amount(),
+            tax_amount: tax_amount.as_amount(),
+            total: total.as_amount(),
+            total_hours,
+            entry_count: entry_count as i32,
+        },
+        projects,
+    })
+}
+
+// The number and path as given, or with the first "-N" suffix that no invoices row or file uses
+// yet. A period can be invoiced again (force, only_unbilled) and a reset counter repeats numbers,
+// and neither may overwrite an earlier invoice's PDF or share its number.
+fn unique_invoice_name(
+    conn: &Connection,
+    number: &str,
+    filename: &str,
+    dir: &Path,
+) -> Result<(String, PathBuf), ProtimerError> {
+    let stem = filename.strip_suffix(".pdf").unwrap_or(filename);
+    let mut n = 1;
+    loop {
+        let (candidate, path) = if n == 1 {
+            (number.to_string(), dir.join(filename))
+        } else {
+            (format!("{}-{}", number, n), dir.join(format!("{}-{}.pdf", stem, n)))
+        };
+        let taken: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM invoices WHERE invoiceNumber = ?1 OR filePath = ?2)",
+            params![candidate, path.to_string_lossy()],
+            |row| row.get(0),
+        )?;
+        if !taken && !path.exists() {
+            return Ok((candidate, path));
+        }
+        n += 1;
+    }
+}
+
+// Numbers, renders and records an invoice. Each project in the draft gets an invoices row
+// with the shared number and PDF, and its share of the total by section subtotal, so
+// per-project overlap checks and totals keep working for combined invoices. The billed entries
+// are linked to their project's row.
+fn issue_invoice(
+    conn: &mut Connection,
+    business: BusinessInfo,
+    draft: InvoiceDraft,
+    start_date: i64,
+    end_date: i64,
+) -> Result<InvoiceResult, ProtimerError> {
+    let start_date_obj = to_local(start_date).ok_or("Invalid start date")?;
+    let end_date_obj = to_local(end_date).ok_or("Invalid end date")?;
+
+    // The counter is read and bumped in the same transaction that records the invoice,
+    // so a failed generation doesn't burn a number
+    let tx = conn.transaction()?;
+
+    let (invoice_number, filename) = if business.invoice_numbering == "sequential" {
+        let counter: i64 = tx
+            .query_row("SELECT invoiceCounter FROM business_info WHERE id = 1", [], |row| row.get(0))?;
+        tx.execute("UPDATE business_info SET invoiceCounter = invoiceCounter + 1 WHERE id = 1", [])?;
+
+        let number = format!("{}{:04}", business.invoice_prefix, counter);
+        // Prefixes are user input, so keep only filename-safe characters (e.g., "invoice_INV-0042.pdf")
+        let safe_number: String = number
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        (number, format!("invoice_{}.pdf", safe_number))
+    } else {
+        // Use date range as invoice "number" (just for display on PDF)
+        let number = format!(
+            "{} to {}",
+            start_date_obj.format("%b %d, %Y"),
+            end_date_obj.format("%b %d, %Y")
+        );
+        // Generate filename from date range (e.g., "invoice_2026-02-02_to_2026-02-08.pdf")
+        let filename = format!(
+            "invoice_{}_to_{}.pdf",
+            start_date_obj.format("%Y-%m-%d"),
+            end_date_obj.format("%Y-%m-%d")
+        );
+        (number, filename)
+    };
+
+    // Generate PDF in project-specific folder
+    let project_dir = invoice::get_project_invoices_dir(&draft.folder_name)?;
+    let (invoice_number, output_path) = unique_invoice_name(&tx, &invoice_number, &filename, &project_dir)?;
+    let created_at = now_ms();
+
+    // Render beside the final file and move it into place only once the rows are committed,
+    // so a failed insert leaves no PDF behind for an invoice that was never recorded
+    let temp_path = output_path.with_extension("pdf.tmp");
+    let recorded = render_invoice(business, draft, &invoice_number, created_at, temp_path.clone()).and_then(|mut rendered| {
+        rendered.result.file_path = output_path.to_string_lossy().to_string();
+        record_invoice(tx, &rendered, &invoice_number, start_date, end_date, created_at)?;
+        Ok(rendered)
+    });
+    let rendered = match recorded {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e);
+        }
+    };
+    fs::rename(&temp_path, &output_path).map_err(|e| {
+        ProtimerError::Io(format!("Invoice {} was recorded but its PDF couldn't be saved: {}", invoice_number, e))
+    })?;
+
+    Ok(rendered.result)
+}
+
+// Insert the invoices rows and entry links for a rendered invoice and commit them
+fn record_invoice(
+    tx: rusqlite::Transaction,
+    rendered: &RenderedInvoice,
+    invoice_number: &str,
+    start_date: i64,
+    end_date: i64,
+    created_at: i64,
+) -> Result<(), ProtimerError> {
+    for project in &rendered.projects {
+        let invoice_id = generate_id();
+        tx.execute(
+            "INSERT INTO invoices (id, invoiceNumber, projectId, filePath, startDate, endDate, totalAmount, createdAt, options)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                invoice_id,
+                invoice_number,
+                project.project_id,
+                rendered.result.file_path,
+                start_date,
+                end_date,
+                project.total.as_amount(),
+                created_at,
+                serde_json::to_string(&project.options)?,
+            ],
+        )?;
+        for entry_id in &project.entry_ids {
+            tx.execute(
+                "INSERT INTO invoice_entries (invoiceId, entryId) VALUES (?1, ?2)",
+                params![invoice_id, entry_id],
+            )?;
+            tx.execute("UPDATE time_entries SET invoiceId = ?1 WHERE id = ?2", params![invoice_id, entry_id])?;
+        }
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+#[tauri::command]
+fn generate_invoice(
+    project_id: String,
+    start_date: i64,
+    end_date: i64,
+    extra_hours: f64,
+    itemize: Option<bool>,
+    billing_increment_minutes: Option<u32>,
+    rounding_mode: Option<invoice::RoundingMode>,
+    force: Option<bool>,
+    adjustments: Option<Vec<(String, f64)>>,
+    only_unbilled: Option<bool>,
+    state: State<AppState>,
+) -> Result<InvoiceResult, ProtimerError> {
+    validate_extra_hours(extra_hours)?;
+    let adjustments = parse_adjustments(adjustments)?;
+    let rounding_mode = rounding_mode.unwrap_or_default();
+    let only_unbilled = only_unbilled.unwrap_or(false);
+
+    let mut conn = lock_recover(&state.db, "database");
+
+    // Get project info
+    let project = load_invoice_project(&conn, &project_id)?;
+
+    // Get business info
+    let business = load_business_info(&conn)?;
+
+    if business.name.is_empty() {
+        return Err(ProtimerError::Validation("Please configure your business information in Settings first".to_string()));
+    }
+
+    // Skipping billed entries already prevents double-billing, so overlapping periods are fine
+    if !force.unwrap_or(false) && !only_unbilled {
+        check_invoice_overlap(&conn, &project, start_date, end_date)?;
+    }
+
+    let (invoice_entries, entry_ids) = build_invoice_rows(
+        &conn,
+        &project,
+        start_date,
+        end_date,
+        extra_hours,
+        itemize.unwrap_or(false),
+        billing_increment_minutes,
+        rounding_mode,
+        BilledEntries::Period { only_unbilled },
+    )?;
+
+    if entry_ids.is_empty() && extra_hours == 0.0 {
+        return Err(ProtimerError::Validation("No time entries found for this date range and no extra hours provided".to_string()));
+    }
+
+    let header_note = invoice_header_note(std::slice::from_ref(&project));
+    let draft = InvoiceDraft {
+        // Fall back to the project name so projects without client details still invoice
+        client_name: project.client_name.unwrap_or_else(|| project.name.clone()),
+        client_email: project.client_email,
+        client_address: project.client_address,
+        project_name: project.name.clone(),
+        header_note,
+        folder_name: project.name,
+        sections: vec![DraftSection {
+            project_id,
+            entry_ids,
+            extra_hours,
+            section: invoice::InvoiceSection { title: None, entries: invoice_entries },
+            extra_row: None,
+        }],
+        adjustments,
+        itemize: itemize.unwrap_or(false),
+        billing_increment_minutes,
+        rounding_mode,
+        taxes: invoice_taxes(&business),
+        tax_inclusive: business.tax_inclusive,
+    };
+
+    issue_invoice(&mut conn, business, draft, start_date, end_date)
+}
+
+// One invoice for a client whose work spans several projects: rows are grouped under each
+// project with its own subtotal, then taxed and totalled together. The projects must bill the
+// same client. Extra hours get a line of their own at the first project's rate, and adjustments
+// apply to the combined subtotal. The PDF is filed under the client's name.
+#[tauri::command]
+fn generate_combined_invoice(
+    project_ids: Vec<String>,
+    start_date: i64,
+    end_date: i64,
+    extra_hours: f64,
+    itemize: Option<bool>,
+    billing_increment_minutes: Option<u32>,
+    rounding_mode: Option<invoice::RoundingMode>,
+    force: Option<bool>,
+    adjustments: Option<Vec<(String, f64)>>,
+    only_unbilled: Option<bool>,
+    state: State<AppState>,
+) -> Result<InvoiceResult, ProtimerError> {
+    validate_extra_hours(extra_hours)?;
+    let adjustments = parse_adjustments(adjustments)?;
+    let rounding_mode = rounding_mode.unwrap_or_default();
+    let only_unbilled = only_unbilled.unwrap_or(false);
+    let mut project_ids = project_ids;
+    let mut seen = std::collections::HashSet::new();
+    project_ids.retain(|id| seen.insert(id.clone()));
+    if project_ids.is_empty() {
+        return Err(ProtimerError::Validation("Choose at least one project to invoice".to_string()));
+    }
+
+    let mut conn = lock_recover(&state.db, "database");
+
+    let projects = project_ids
+        .iter()
+        .map(|id| load_invoice_project(&conn, id))
+        .collect::<Result<Vec<_>, ProtimerError>>()?;
+
+    // Every project must carry the same client details, or the invoice can't have one addressee
+    let client = |p: &InvoiceProject| (p.client_name.clone(), p.client_email.clone(), p.client_address.clone());
+    let first = &projects[0];
+    if let Some(other) = projects.iter().find(|p| client(p) != client(first)) {
+        return Err(ProtimerError::Validation(format!(
+            "{} and {} have different client details; they can't share an invoice",
+            first.name, other.name
+        )));
+    }
+    let client_name = first
+        .client_name
+        .clone()
+        .ok_or("Set the client details on these projects before combining them into one invoice")?;
+
+    let business = load_business_info(&conn)?;
+
+    if business.name.is_empty() {
+        return Err(ProtimerError::Validation("Please configure your business information in Settings first".to_string()));
+    }
+
+    if !force.unwrap_or(false) && !only_unbilled {
+        for project in &projects {
+            check_invoice_overlap(&conn, project, start_date, end_date)?;
+        }
+    }
+
+    let mut sections = Vec::with_capacity(projects.len());
+    for (i, project) in projects.iter().enumerate() {
+        let project_extra_hours = if i == 0 { extra_hours } else { 0.0 };
+        let extra_row = if project_extra_hours > 0.0 {
+            Some(extra_hours_row(&conn, project, start_date, end_date, project_extra_hours)?)
+        } else {
+            None
+        };
+        let (entries, entry_ids) = build_invoice_rows(
+            &conn,
+            project,
+            start_date,
+            end_date,
+            0.0,
+            itemize.unwrap_or(false),
+            billing_increment_minutes,
+            rounding_mode,
+            BilledEntries::Period { only_unbilled },
+        )?;
+        // Projects with nothing billable in the period are left off
+        if !entries.is_empty() || extra_row.is_some() {
+            sections.push(DraftSection {
+                project_id: project.id.clone(),
+                entry_ids,
+                extra_hours: project_extra_hours,
+                section: invoice::InvoiceSection { title: Some(project.name.clone()), entries },
+                extra_row,
+            });
+        }
+    }
+
+    if sections.iter().all(|s| s.entry_ids.is_empty()) && extra_hours == 0.0 {
+        return Err(ProtimerError::Validation("No time entries found for this date range and no extra hours provided".to_string()));
+    }
+
+    let draft = InvoiceDraft {
+        client_email: first.client_email.clone(),
+        client_address: first.client_address.clone(),
+        project_name: projects.iter().map(|p| p.name.as_str()).collect::<Vec<_>>().join(", "),
+        header_note: invoice_header_note(&projects),
+        folder_name: client_name.clone(),
+        client_name,
+        sections,
+        adjustments,
+        itemize: itemize.unwrap_or(false),
+        billing_increment_minutes,
+        rounding_mode,
+        taxes: invoice_taxes(&business),
+        tax_inclusive: business.tax_inclusive,
+    };
+
+    issue_invoice(&mut conn, business, draft, start_date, end_date)
+}
+
+// Re-render an issued invoice's PDF over its recorded file, e.g. after fixing the business name
+// or logo. Rows are rebuilt from the entries the invoice billed, with the options and taxes it
+// was issued with, so amounts only change if those entries were edited since. The number and
+// dates stay as issued. Older date-range invoices can share a number; project_id picks one.
+#[tauri::command]
+fn regenerate_invoice(invoice_number: String, project_id: Option<String>, state: State<AppState>) -> Result<String, ProtimerError> {
+    let mut conn = lock_recover(&state.db, "database");
+
+    let mut stmt = conn.prepare(
+        "SELECT id, projectId, filePath, startDate, endDate, createdAt, options FROM invoices
+         WHERE invoiceNumber = ?1 ORDER BY rowid",
+    )?;
+    let rows: Vec<(String, String, String, i64, i64, i64, Option<String>)> = stmt
+        .query_map(params![invoice_number], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?))
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    drop(stmt);
+
+    // A combined invoice has a row per project, all pointing at the same PDF
+    let file_path = match &project_id {
+        Some(id) => rows.iter().find(|r| &r.1 == id),
+        None => rows.first(),
+    }
+    .map(|r| r.2.clone())
+    .ok_or_else(|| ProtimerError::NotFound(format!("Invoice not found: {}", invoice_number)))?;
+    if project_id.is_none() && rows.iter().any(|r| r.2 != file_path) {
+        return Err(ProtimerError::Validation(format!(
+            "More than one invoice is numbered {}; choose the project to regenerate",
+            invoice_number
+        )));
+    }
+    let rows: Vec<_> = rows.into_iter().filter(|r| r.2 == file_path).collect();
+
+    let business = load_business_info(&conn)?;
+
+    let mut projects = Vec::with_capacity(rows.len());
+    let mut sections = Vec::with_capacity(rows.len());
+    let mut row_options = Vec::with_capacity(rows.len());
+    for (invoice_id, project_id, _, start_date, end_date, _, options) in &rows {
+        let options_recorded = options.is_some();
+        // Invoices issued before options were recorded were built with the defaults and
+        // the taxes configured now
+        let options: InvoiceOptions = match options {
+            Some(json) => serde_json::from_str(json)?,
+            None => InvoiceOptions {
+                itemize: false,
+                billing_increment_minutes: None,
+                rounding_mode: invoice::RoundingMode::default(),
+                extra_hours: 0.0,
+                adjustments: Vec::new(),
+                taxes: invoice_taxes(&business),
+                tax_inclusive: business.tax_inclusive,
+                combined: rows.len() > 1,
+            },
+        };
+
+        // Invoices from before entries were linked fall back to the billed period
+        let linked: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM invoice_entries WHERE invoiceId = ?1)",
+            params![invoice_id],
+            |row| row.get(0),
+        )?;
+        let billed_entries = if linked || options_recorded {
+            BilledEntries::Linked(invoice_id)
+        } else {
+            BilledEntries::Period { only_unbilled: false }
+        };
+
+        let project = load_invoice_project(&conn, project_id)?;
+        // Combined invoices show extra hours on their own line; single ones fold them into the rows
+        let extra_row = if options.combined && options.extra_hours > 0.0 {
+            Some(extra_hours_row(&conn, &project, *start_date, *end_date, options.extra_hours)?)
+        } else {
+            None
+        };
+        let (entries, entry_ids) = build_invoice_rows(
+            &conn,
+            &project,
+            *start_date,
+            *end_date,
+            if extra_row.is_some() { 0.0 } else { options.extra_hours },
+            options.itemize,
+            options.billing_increment_minutes,
+            options.rounding_mode,
+            billed_entries,
+        )?;
+        sections.push(DraftSection {
+            project_id: project.id.clone(),
+            entry_ids,
+            extra_hours: options.extra_hours,
+            section: invoice::InvoiceSection { title: options.combined.then(|| project.name.clone()), entries },
+            extra_row,
+        });
+        projects.push(project);
+        row_options.push(options);
+    }
+
+    // Invoice-wide settings are the same on every row
+    let first_options = &row_options[0];
+    let first = &projects[0];
+    let draft = InvoiceDraft {
+        client_name: first.client_name.clone().unwrap_or_else(|| first.name.clone()),
+        client_email: first.client_email.clone(),
+        client_address: first.client_address.clone(),
+        project_name: projects.iter().map(|p| p.name.as_str()).collect::<Vec<_>>().join(", "),
+        header_note: invoice_header_note(&projects),
+        // Not used: the PDF is rewritten at its recorded path
+        folder_name: String::new(),
+        sections,
+        adjustments: first_options
+            .adjustments
+            .iter()
+            .map(|(label, amount)| invoice::InvoiceAdjustment { label: label.clone(), amount: Cents::from_amount(*amount) })
+            .collect(),
+        itemize: first_options.itemize,
+        billing_increment_minutes: first_options.billing_increment_minutes,
+        rounding_mode: first_options.rounding_mode,
+        taxes: first_options.taxes.clone(),
+        tax_inclusive: first_options.tax_inclusive,
+    };
+
+    let created_at = rows[0].5;
+    let rendered = render_invoice(business, draft, &invoice_number, created_at, PathBuf::from(&file_path))?;
+
+    // Keep the recorded totals in step with the PDF, and pin older invoices to the entries and
+    // options just used so later regenerations match
+    let tx = conn.transaction()?;
+    for (row, project) in rows.iter().zip(&rendered.projects) {
+        tx.execute(
+            "UPDATE invoices SET totalAmount = ?1, options = ?2 WHERE id = ?3",
+            params![project.total.as_amount(), serde_json::to_string(&project.options)?, row.0],
+        )?;
+        for entry_id in &project.entry_ids {
+            tx.execute(
+                "INSERT OR IGNORE INTO invoice_entries (invoiceId, entryId) VALUES (?1, ?2)",
+                params![row.0, entry_id],
+            )?;
+            tx.execute(
+                "UPDATE time_entries SET invoiceId = ?1 WHERE id = ?2 AND invoiceId IS NULL",
+                params![row.0, entry_id],
+            )?;
+        }
+    }
+    tx.commit()?;
+
+    Ok(rendered.result.file_path)
+}
+
+#[tauri::command]
+fn get_invoices(state: State<AppState>) -> Result<Vec<InvoiceRecord>, ProtimerError> {
+    let conn = state.readers.get();
+
+    let mut stmt = conn
+        .prepare("SELECT i.invoiceNumber, i.projectId, i.filePath, i.startDate, i.endDate, i.totalAmount, i.createdAt, p.name
+                  FROM invoices i
+                  LEFT JOIN projects p ON i.projectId = p.id
+                  ORDER BY i.createdAt DESC")?;
+
+    let invoices: Vec<InvoiceRecord> = stmt
+        .query_map([], |row| {
+            Ok(InvoiceRecord {
+                invoice_number: row.get(0)?,
+                project_id: row.get(1)?,
+                file_path: row.get(2)?,
+                start_date: row.get(3)?,
+                end_date: row.get(4)?,
+                total_amount: row.get(5)?,
+                created_at: row.get(6)?,
+                project_name: row.get::<_, Option<String>>(7)?.unwrap_or_else(|| "Unknown".to_string()),
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(invoices)
+}
+
+// Quote a CSV field when it holds a delimiter, quote or line break, doubling any quotes inside
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Every invoice created within [start, end] (either bound optional) as a CSV for bookkeeping,
+// oldest first. Combined invoices have one row per project. Returns the file's path.
+#[tauri::command]
+fn export_invoices_csv(start: Option<i64>, end: Option<i64>, state: State<AppState>) -> Result<String, ProtimerError> {
+    if let (Some(start), Some(end)) = (start, end) {
+        if start > end {
+            return Err(ProtimerError::Validation("Start must be before end".to_string()));
+        }
+    }
+
+    let conn = state.readers.get();
+    let currency = load_business_info(&conn)?.currency;
+
+    let mut stmt = conn.prepare(
+        "SELECT i.invoiceNumber, p.name, i.startDate, i.endDate, i.createdAt, i.totalAmount
+         FROM invoices i
+         LEFT JOIN projects p ON i.projectId = p.id
+         WHERE (?1 IS NULL OR i.createdAt >= ?1) AND (?2 IS NULL OR i.createdAt <= ?2)
+         ORDER BY i.createdAt, i.invoiceNumber",
+    )?;
+    let rows = stmt
+        .query_map(params![start, end], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?.unwrap_or_else(|| "Unknown".to_string()),
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, f64>(5)?,
+            ))
+        })?
+        .filter_map(|r| r.ok())
+        .collect::<Vec<_>>();
 
-    Ok(entry)
+    let iso_date = |ms: i64| to_local(ms).map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default();
+    let mut content = String::from("Invoice Number,Project,Period Start,Period End,Issued,Amount,Currency\n");
+    for (invoice_number, project_name, start_date, end_date, created_at, total_amount) in &rows {
+        content.push_str(&format!(
+            "{},{},{},{},{},{:.2},{}\n",
+            csv_field(invoice_number),
+            csv_field(project_name),
+            iso_date(*start_date),
+            iso_date(*end_date),
+            iso_date(*created_at),
+            total_amount,
+            csv_field(&currency),
+        ));
+    }
+
+    let filename = format!("protimer-invoices-{}.csv", local_now().format("%Y-%m-%d_%H%M%S"));
+    let export_path = get_data_dir()?.join(filename);
+    fs::write(&export_path, content)
+        .map_err(|e| ProtimerError::Io(format!("Failed to write invoices CSV: {}", e)))?;
+
+    Ok(export_path.to_string_lossy().to_string())
 }
 
 #[tauri::command]
-fn get_weekly_summary(state: State<AppState>) -> Result<WeeklySummary, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+fn get_unbilled_total(project_id: String, state: State<AppState>) -> Result<UnbilledTotal, ProtimerError> {
+    let conn = state.readers.get();
 
-    use chrono::{Datelike, Duration, Local};
-    let now = Local::now();
-    let day_of_week = now.weekday().num_days_from_sunday();
-    let days_to_last_sunday = if day_of_week == 0 { 7 } else { day_of_week as i64 };
-    let days_to_last_monday = days_to_last_sunday + 6;
+    let project = load_invoice_project(&conn, &project_id)?;
+    let rate_periods = load_rate_periods(&conn, &project_id)?;
 
-    let last_monday = (now.date_naive() - Duration::days(days_to_last_monday))
-        .and_hms_opt(0, 0, 0)
-        .unwrap()
-        .and_local_timezone(Local)
-        .unwrap();
+    let mut stmt = conn.prepare(
+        "SELECT startTime, endTime FROM time_entries
+         WHERE projectId = ?1 AND endTime IS NOT NULL AND invoiceId IS NULL AND deletedAt IS NULL",
+    )?;
+    let entries = stmt
+        .query_map(params![project_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?
+        .filter_map(|r| r.ok())
+        .collect::<Vec<_>>();
 
-    let last_sunday = (now.date_naive() - Duration::days(days_to_last_sunday))
-        .and_hms_opt(23, 59, 59)
-        .unwrap()
-        .and_local_timezone(Local)
-        .unwrap();
+    let mut total_ms = 0;
+    let mut amount = Some(Cents(0));
+    for (start_time, end_time) in &entries {
+        let duration_ms = end_time - start_time;
+        total_ms += duration_ms;
+        amount = match (amount, rate_at(&rate_periods, *start_time, project.hourly_rate)) {
+            (Some(total), Some(rate)) => Some(total + Cents::for_hours(duration_ms as f64 / 3600000.0, rate)),
+            _ => None,
+        };
+    }
 
-    let last_monday_ms = last_monday.timestamp_millis();
-    let last_sunday_ms = last_sunday.timestamp_millis();
+    Ok(UnbilledTotal {
+        project_id,
+        total_ms,
+        total_hours: (total_ms as f64 / 3600000.0 * 100.0).round() / 100.0,
+        entry_count: entries.len() as i32,
+        amount: amount.map(Cents::as_amount),
+    })
+}
 
-    // Get projects with hourly rates
-    let mut stmt = conn
-        .prepare("SELECT id, name, hourlyRate FROM projects")
-        .map_err(|e| e.to_string())?;
+// Time entries billed by an invoice, for checking exactly what a client was charged for.
+// Covers every project on a combined invoice. Deleted entries stay listed, as they do on a
+// regenerated PDF, flagged with deleted_at.
+#[tauri::command]
+fn get_invoice_entries(invoice_number: String, state: State<AppState>) -> Result<Vec<BilledEntry>, ProtimerError> {
+    let conn = state.readers.get();
+
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM invoices WHERE invoiceNumber = ?1)",
+        params![invoice_number],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        return Err(ProtimerError::NotFound(format!("Invoice not found: {}", invoice_number)));
+    }
 
-    let projects: Vec<(String, String, Option<f64>)> = stmt
-        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
-        .map_err(|e| e.to_string())?
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, deletedAt FROM time_entries
+         WHERE id IN (
+            SELECT ie.entryId FROM invoice_entries ie JOIN invoices i ON i.id = ie.invoiceId
+            WHERE i.invoiceNumber = ?1
+         )
+         ORDER BY startTime ASC",
+        TIME_ENTRY_COLUMNS
+    ))?;
+    let entries = stmt
+        .query_map(params![invoice_number], |row| {
+            Ok(BilledEntry { entry: time_entry_from_row(row)?, deleted_at: row.get(8)? })
+        })?
         .filter_map(|r| r.ok())
         .collect();
 
-    let mut summary_projects = Vec::new();
-    let mut total_earnings: f64 = 0.0;
+    Ok(entries)
+}
 
-    for (project_id, project_name, hourly_rate) in projects {
-        let (total_ms, entry_count): (i64, i32) = conn
-            .query_row(
-                "SELECT COALESCE(SUM(COALESCE(endTime, startTime) - startTime), 0), COUNT(*) FROM time_entries WHERE projectId = ?1 AND startTime >= ?2 AND startTime <= ?3",
-                params![project_id, last_monday_ms, last_sunday_ms],
-                |row| Ok((row.get(0)?, row.get(1)?)),
-            )
-            .unwrap_or((0, 0));
+// ============== INTEGRITY ==============
 
-        if total_ms > 0 {
-            let total_hours = (total_ms as f64 / 3600000.0 * 100.0).round() / 100.0;
-            let earnings = hourly_rate.map(|rate| (total_hours * rate * 100.0).round() / 100.0);
+fn scan_integrity(conn: &Connection) -> rusqlite::Result<IntegrityReport> {
+    let ids = |sql: &str| -> rusqlite::Result<Vec<String>> {
+        let mut stmt = conn.prepare(sql)?;
+        let found = stmt.query_map([], |row| row.get(0))?.filter_map(|r| r.ok()).collect();
+        Ok(found)
+    };
 
-            if let Some(e) = earnings {
-                total_earnings += e;
-            }
+    let inverted_entries = ids("SELECT id FROM time_entries WHERE endTime IS NOT NULL AND endTime < startTime ORDER BY startTime")?;
+    let orphaned_entries = ids(
+        "SELECT id FROM time_entries WHERE projectId NOT IN (SELECT id FROM projects) ORDER BY startTime",
+    )?;
+    let orphaned_sessions = ids("SELECT projectId FROM active_sessions WHERE projectId NOT IN (SELECT id FROM projects)")?;
+
+    // Sweep each project's live entries in start order, comparing against the furthest-reaching
+    // entry so far. Open entries run until now, as in find_overlapping_entry.
+    let mut stmt = conn.prepare(
+        "SELECT id, projectId, startTime, endTime FROM time_entries
+         WHERE deletedAt IS NULL AND (endTime IS NULL OR endTime >= startTime)
+         ORDER BY projectId, startTime",
+    )?;
+    let entries: Vec<(String, String, i64, Option<i64>)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+        .filter_map(|r| r.ok())
+        .collect();
 
-            summary_projects.push(WeeklySummaryProject {
-                project_id,
-                project_name,
-                total_ms,
-                total_hours,
-                entry_count,
-                hourly_rate,
-                earnings,
-            });
+    let now = now_ms();
+    let mut overlapping_entries = Vec::new();
+    let mut furthest: Option<(&str, &str, i64)> = None;
+    for (id, project_id, start_time, end_time) in &entries {
+        let end_time = end_time.unwrap_or(now);
+        match furthest {
+            Some((prev_project, prev_id, prev_end)) if prev_project == project_id.as_str() => {
+                if *start_time < prev_end {
+                    overlapping_entries.push(EntryOverlap {
+                        project_id: project_id.clone(),
+                        first_entry_id: prev_id.to_string(),
+                        second_entry_id: id.clone(),
+                    });
+                }
+                if end_time > prev_end {
+                    furthest = Some((project_id.as_str(), id.as_str(), end_time));
+                }
+            }
+            _ => furthest = Some((project_id.as_str(), id.as_str(), end_time)),
         }
     }
 
-    Ok(WeeklySummary {
-        week_start: last_monday.to_rfc3339(),
-        week_end: last_sunday.to_rfc3339(),
-        projects: summary_projects,
-        total_earnings,
+    Ok(IntegrityReport {
+        inverted_entries,
+        overlapping_entries,
+        orphaned_entries,
+        orphaned_sessions,
     })
 }
 
-// ============== BUSINESS INFO & INVOICE COMMANDS ==============
+// Check the database for problems that skew totals. Read-only; see repair_integrity.
+#[tauri::command]
+fn verify_integrity(state: State<AppState>) -> Result<IntegrityReport, ProtimerError> {
+    let conn = state.readers.get();
+    scan_integrity(&conn).map_err(ProtimerError::from)
+}
 
+// Delete inverted entries, orphaned entries and orphaned sessions, then report what remains.
+// Overlaps need a human to decide which entry is right, so they are only reported.
 #[tauri::command]
-fn get_business_info(state: State<AppState>) -> Result<BusinessInfo, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+fn repair_integrity(state: State<AppState>) -> Result<IntegrityReport, ProtimerError> {
+    let conn = lock_recover(&state.db, "database");
 
-    let (name, email, tax_rate): (String, String, f64) = conn
-        .query_row(
-            "SELECT name, email, taxRate FROM business_info WHERE id = 1",
-            [],
-            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
-        )
-        .map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM time_entries WHERE endTime IS NOT NULL AND endTime < startTime", [])?;
+    tx.execute("DELETE FROM time_entries WHERE projectId NOT IN (SELECT id FROM projects)", [])?;
+    tx.execute("DELETE FROM active_sessions WHERE projectId NOT IN (SELECT id FROM projects)", [])?;
+    tx.commit()?;
 
-    Ok(BusinessInfo {
-        name,
-        email: if email.is_empty() { None } else { Some(email) },
-        tax_rate,
-    })
+    scan_integrity(&conn).map_err(ProtimerError::from)
 }
 
-#[tauri::command]
-fn save_business_info(
-    name: String,
-    email: Option<String>,
-    tax_rate: f64,
-    state: State<AppState>,
-) -> Result<(), String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+// ============== JSON EXPORT / IMPORT ==============
+
+// Tables included in a JSON export, ordered so projects are imported before rows that reference them
+const EXPORT_TABLES: [&str; 8] = [
+    "projects",
+    "project_rates",
+    "business_info",
+    "settings",
+    "time_entries",
+    "active_sessions",
+    "invoices",
+    "invoice_entries",
+];
+
+fn get_table_columns(conn: &Connection, table: &str) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(columns)
+}
 
-    conn.execute(
-        "UPDATE business_info SET name = ?1, email = ?2, taxRate = ?3 WHERE id = 1",
-        params![name, email.unwrap_or_default(), tax_rate],
-    )
-    .map_err(|e| e.to_string())?;
+fn sql_to_json(value: rusqlite::types::Value) -> serde_json::Value {
+    use rusqlite::types::Value;
+    match value {
+        Value::Null | Value::Blob(_) => serde_json::Value::Null,
+        Value::Integer(i) => serde_json::json!(i),
+        Value::Real(f) => serde_json::json!(f),
+        Value::Text(t) => serde_json::Value::String(t),
+    }
+}
 
-    Ok(())
+fn json_to_sql(value: &serde_json::Value) -> rusqlite::types::Value {
+    use rusqlite::types::Value;
+    match value {
+        serde_json::Value::Bool(b) => Value::Integer(if *b { 1 } else { 0 }),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or(0.0)),
+        },
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        _ => Value::Null,
+    }
 }
 
+#[tauri::command]
+fn export_database_json(state: State<AppState>) -> Result<String, ProtimerError> {
+    let conn = state.readers.get();
+
+    let mut tables = serde_json::Map::new();
+    for table in EXPORT_TABLES {
+        let mut stmt = conn
+            .prepare(&format!("SELECT * FROM {}", table))?;
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+
+        let rows: Vec<serde_json::Value> = stmt
+            .query_map([], |row| {
+                let mut obj = serde_json::Map::new();
+                for (i, column) in columns.iter().enumerate() {
+                    obj.insert(column.clone(), sql_to_json(row.get(i)?));
+                }
+                Ok(serde_json::Value::Object(obj))
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        tables.insert(table.to_string(), serde_json::Value::Array(rows));
+    }
+
+    let export = serde_json::json!({
+        "version": 1,
+        "exportedAt": now_ms(),
+        "tables": tables,
+    });
+
+    let filename = format!("protimer-export-{}.json", chrono::Local::now().format("%Y-%m-%d_%H%M%S"));
+    let export_path = get_data_dir()?.join(filename);
+    let content = serde_json::to_string_pretty(&export)
+        .map_err(|e| ProtimerError::Io(format!("Failed to serialize export: {}", e)))?;
+    fs::write(&export_path, content)
+        .map_err(|e| ProtimerError::Io(format!("Failed to write export file: {}", e)))?;
+
+    Ok(export_path.to_string_lossy().to_string())
+}
 
 #[tauri::command]
-fn generate_invoice(
-    project_id: String,
-    start_date: i64,
-    end_date: i64,
-    extra_hours: f64,
-    state: State<AppState>,
-) -> Result<String, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+fn import_database_json(path: String, state: State<AppState>) -> Result<(), ProtimerError> {
+    let content = fs::read_to_string(&path)
+        .map_err(|e| ProtimerError::Io(format!("Failed to read export file: {}", e)))?;
+    let export: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| ProtimerError::Validation(format!("Invalid export file: {}", e)))?;
+    let tables = export
+        .get("tables")
+        .and_then(|t| t.as_object())
+        .ok_or("Invalid export file: missing tables")?;
+
+    let mut conn = lock_recover(&state.db, "database");
+
+    // All-or-nothing so a malformed file can't leave the DB half-populated
+    let tx = conn.transaction()?;
+
+    for table in EXPORT_TABLES {
+        let rows = match tables.get(table).and_then(|r| r.as_array()) {
+            Some(rows) => rows,
+            None => continue,
+        };
 
-    // Get project info
-    let (project_name, hourly_rate): (String, Option<f64>) = conn
-        .query_row(
-            "SELECT name, hourlyRate FROM projects WHERE id = ?1",
-            params![project_id],
-            |row| Ok((row.get(0)?, row.get(1)?)),
-        )
-        .map_err(|e| e.to_string())?;
+        // Only accept columns that exist in our schema
+        let known_columns = get_table_columns(&tx, table)?;
+
+        for row in rows {
+            let obj = row
+                .as_object()
+                .ok_or_else(|| ProtimerError::Validation(format!("Invalid export file: malformed row in {}", table)))?;
+
+            // Skip rows whose project no longer resolves
+            if table != "projects" {
+                if let Some(project_id) = obj.get("projectId").and_then(|v| v.as_str()) {
+                    let exists: bool = tx
+                        .query_row(
+                            "SELECT EXISTS(SELECT 1 FROM projects WHERE id = ?1)",
+                            params![project_id],
+                            |row| row.get(0),
+                        )?;
+                    if !exists {
+                        continue;
+                    }
+                }
+            }
 
-    let rate = hourly_rate.ok_or("Project must have an hourly rate set")?;
+            let (columns, values): (Vec<&str>, Vec<rusqlite::types::Value>) = obj
+                .iter()
+                .filter(|(column, _)| known_columns.contains(*column))
+                .map(|(column, value)| (column.as_str(), json_to_sql(value)))
+                .unzip();
 
-    // Get business info
-    let (business_name, business_email, tax_rate): (String, String, f64) = conn
-        .query_row(
-            "SELECT name, email, taxRate FROM business_info WHERE id = 1",
-            [],
-            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
-        )
-        .map_err(|e| e.to_string())?;
+            if columns.is_empty() {
+                continue;
+            }
 
-    if business_name.is_empty() {
-        return Err("Please configure your business information in Settings first".to_string());
+            let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+            let sql = format!(
+                "INSERT OR REPLACE INTO {} ({}) VALUES ({})",
+                table,
+                columns.join(", "),
+                placeholders.join(", ")
+            );
+            tx.execute(&sql, rusqlite::params_from_iter(values))
+                .map_err(|e| ProtimerError::Database(format!("Failed to import {} row: {}", table, e)))?;
+        }
     }
 
-    // Get time entries for the period
-    let mut stmt = conn
-        .prepare(
-            "SELECT startTime, endTime, description FROM time_entries
-             WHERE projectId = ?1 AND startTime >= ?2 AND startTime <= ?3
-             ORDER BY startTime ASC",
-        )
-        .map_err(|e| e.to_string())?;
+    tx.commit()?;
+    refresh_timezone_override(&conn);
 
-    let entries_data = stmt
-        .query_map(params![project_id, start_date, end_date], |row| {
-            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<i64>>(1)?, row.get::<_, Option<String>>(2)?))
-        })
-        .map_err(|e| e.to_string())?
-        .filter_map(|r| r.ok())
-        .collect::<Vec<_>>();
+    Ok(())
+}
 
-    if entries_data.is_empty() && extra_hours == 0.0 {
-        return Err("No time entries found for this date range and no extra hours provided".to_string());
-    }
+// ============== BACKUPS ==============
+
+// Number of timestamped backups kept in the backups folder; older ones are pruned
+const BACKUP_KEEP: usize = 10;
+
+// How often the backup thread wakes to check whether a backup is due
+const BACKUP_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
 
-    // Calculate total hours
-    use chrono::{DateTime, Local};
-    let mut total_hours = 0.0;
+fn get_backups_dir() -> Result<PathBuf, ProtimerError> {
+    let dir = get_data_dir()?.join("backups");
 
-    for (start_time, end_time, _description) in &entries_data {
-        let duration_ms = end_time.unwrap_or(*start_time) - start_time;
-        let hours = duration_ms as f64 / 3600000.0;
-        total_hours += hours;
+    if !dir.exists() {
+        fs::create_dir_all(&dir).map_err(|e| ProtimerError::Io(format!("Failed to create backups directory: {}", e)))?;
     }
 
-    // Add extra hours tracked outside of ProTimer
-    total_hours += extra_hours;
+    Ok(dir)
+}
 
-    // Round to 2 decimal places
-    total_hours = (total_hours * 100.0).round() / 100.0;
+// Backups are named data-YYYYMMDD-HHMMSS.db, so sorting by name puts the oldest first
+fn list_backups(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut backups: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("data-") && name.ends_with(".db"))
+        })
+        .collect();
+    backups.sort();
+    Ok(backups)
+}
 
-    // Format date range for the invoice entry
-    let start_date_obj = DateTime::from_timestamp_millis(start_date)
-        .ok_or("Invalid start date")?
-        .with_timezone(&Local);
-    let end_date_obj = DateTime::from_timestamp_millis(end_date)
-        .ok_or("Invalid end date")?
-        .with_timezone(&Local);
+// Copy the database with SQLite's online backup API, which snapshots consistently even while
+// another connection is writing, then prune down to BACKUP_KEEP
+fn create_backup(conn: &Connection) -> Result<PathBuf, ProtimerError> {
+    let dir = get_backups_dir()?;
+    let path = dir.join(format!("data-{}.db", chrono::Local::now().format("%Y%m%d-%H%M%S")));
 
-    let date_range = format!(
-        "{} - {}",
-        start_date_obj.format("%b %d, %Y"),
-        end_date_obj.format("%b %d, %Y")
-    );
+    conn.backup(DatabaseName::Main, &path, None)
+        .map_err(|e| ProtimerError::Database(format!("Failed to back up database: {}", e)))?;
 
-    // Create single invoice entry
-    let amount = (total_hours * rate * 100.0).round() / 100.0;
-    let invoice_entries = vec![invoice::InvoiceEntry {
-        date: date_range,
-        hours: total_hours,
-        rate,
-        amount,
-    }];
+    let backups = list_backups(&dir)?;
+    for old in backups.iter().take(backups.len().saturating_sub(BACKUP_KEEP)) {
+        let _ = fs::remove_file(old);
+    }
 
-    let subtotal = amount;
-    let tax_amount = ((subtotal * tax_rate / 100.0) * 100.0).round() / 100.0;
-    let total = ((subtotal + tax_amount) * 100.0).round() / 100.0;
+    Ok(path)
+}
 
-    // Create invoice data
-    let invoice_date = Local::now().format("%Y-%m-%d").to_string();
+// True when no backup exists yet or the newest one is older than the interval
+fn backup_due(interval_hours: i64) -> bool {
+    let newest_age = get_backups_dir()
+        .ok()
+        .and_then(|dir| list_backups(&dir).ok())
+        .and_then(|backups| backups.last().cloned())
+        .and_then(|path| fs::metadata(path).ok())
+        .and_then(|meta| meta.modified().ok())
+        .and_then(|modified| modified.elapsed().ok());
 
-    // Generate filename from date range (e.g., "invoice_2026-02-02_to_2026-02-08.pdf")
-    let filename = format!(
-        "invoice_{}_to_{}.pdf",
-        start_date_obj.format("%Y-%m-%d"),
-        end_date_obj.format("%Y-%m-%d")
-    );
+    newest_age.map_or(true, |age| age >= Duration::from_secs(interval_hours as u64 * 3600))
+}
 
-    // Use date range as invoice "number" (just for display on PDF)
-    let invoice_number = format!(
-        "{} to {}",
-        start_date_obj.format("%b %d, %Y"),
-        end_date_obj.format("%b %d, %Y")
-    );
+// Background loop started from run(). Re-reads the interval every check so a change in
+// Settings applies without a restart; an interval of 0 turns automatic backups off.
+fn run_backup_loop(app_handle: tauri::AppHandle) {
+    loop {
+        let state = app_handle.state::<AppState>();
+        let interval_hours = get_backup_interval_hours(&state.readers.get());
+
+        if interval_hours > 0 && backup_due(interval_hours) {
+            // Back up from a read connection so writes aren't blocked while pages are copied
+            if let Err(e) = create_backup(&state.readers.get()) {
+                eprintln!("Automatic backup failed: {}", e);
+            }
+        }
 
-    let invoice_data = invoice::InvoiceData {
-        invoice_number: invoice_number.clone(),
-        invoice_date,
-        business_name,
-        business_email: if business_email.is_empty() { None } else { Some(business_email) },
-        project_name: project_name.clone(),
-        entries: invoice_entries,
-        subtotal,
-        tax_rate,
-        tax_amount,
-        total,
-    };
+        std::thread::sleep(BACKUP_CHECK_INTERVAL);
+    }
+}
 
-    // Generate PDF in project-specific folder
-    let project_dir = invoice::get_project_invoices_dir(&project_name);
-    let output_path = project_dir.join(&filename);
+#[tauri::command]
+fn create_backup_now(state: State<AppState>) -> Result<String, ProtimerError> {
+    let conn = state.readers.get();
+    let path = create_backup(&conn)?;
+    Ok(path.to_string_lossy().to_string())
+}
 
-    let pdf_path = invoice::generate_invoice_pdf(invoice_data, output_path)?;
+// Refuse anything that isn't an intact ProTimer database this build can migrate
+fn validate_backup(path: &Path) -> Result<(), ProtimerError> {
+    if !path.is_file() {
+        return Err(ProtimerError::NotFound(format!("Backup not found: {}", path.display())));
+    }
 
-    // Save invoice record to database
-    let invoice_id = generate_id();
-    conn.execute(
-        "INSERT INTO invoices (id, invoiceNumber, projectId, filePath, startDate, endDate, totalAmount, createdAt)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-        params![invoice_id, invoice_number, project_id, pdf_path, start_date, end_date, total, now_ms()],
-    )
-    .map_err(|e| e.to_string())?;
+    let unreadable = |e: rusqlite::Error| ProtimerError::Validation(format!("Not a readable SQLite database: {}", e));
+    let backup = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(unreadable)?;
+
+    let check: String = backup.query_row("PRAGMA quick_check", [], |row| row.get(0)).map_err(unreadable)?;
+    if check != "ok" {
+        return Err(ProtimerError::Validation(format!("Backup failed its integrity check: {}", check)));
+    }
+
+    let latest_version = BASELINE_SCHEMA_VERSION + MIGRATIONS.len() as i64;
+    if schema_version(&backup).map_err(unreadable)? > latest_version {
+        return Err(ProtimerError::Validation("Backup was made by a newer version of ProTimer".to_string()));
+    }
+
+    // Tables every schema version has; newer ones are created by migrations after the restore
+    for table in ["projects", "business_info", "time_entries", "active_sessions", "invoices"] {
+        let exists: bool = backup
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                params![table],
+                |row| row.get(0),
+            )
+            .map_err(unreadable)?;
+        if !exists {
+            return Err(ProtimerError::Validation(format!("Backup is missing the {} table", table)));
+        }
+    }
 
-    Ok(pdf_path)
+    Ok(())
 }
 
+// Replace the live database with a backup. The current data is first copied to
+// backups/pre-restore-*.db (never pruned) so a mistaken restore can be undone.
+// Pages are copied into the open write connection with the backup API rather than swapping
+// files, which would leave the live -wal file to be replayed over the restored data.
 #[tauri::command]
-fn get_invoices(state: State<AppState>) -> Result<Vec<InvoiceRecord>, String> {
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
+fn restore_backup(path: String, state: State<AppState>) -> Result<(), ProtimerError> {
+    let path = PathBuf::from(path);
+    validate_backup(&path)?;
 
-    let mut stmt = conn
-        .prepare("SELECT i.invoiceNumber, i.projectId, i.filePath, i.startDate, i.endDate, i.totalAmount, i.createdAt, p.name
-                  FROM invoices i
-                  LEFT JOIN projects p ON i.projectId = p.id
-                  ORDER BY i.createdAt DESC")
-        .map_err(|e| e.to_string())?;
+    let mut conn = lock_recover(&state.db, "database");
 
-    let invoices: Vec<InvoiceRecord> = stmt
-        .query_map([], |row| {
-            Ok(InvoiceRecord {
-                invoice_number: row.get(0)?,
-                project_id: row.get(1)?,
-                file_path: row.get(2)?,
-                start_date: row.get(3)?,
-                end_date: row.get(4)?,
-                total_amount: row.get(5)?,
-                created_at: row.get(6)?,
-                project_name: row.get::<_, Option<String>>(7)?.unwrap_or_else(|| "Unknown".to_string()),
-            })
-        })
-        .map_err(|e| e.to_string())?
-        .filter_map(|r| r.ok())
-        .collect();
+    let safety_path = get_backups_dir()?.join(format!("pre-restore-{}.db", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    conn.backup(DatabaseName::Main, &safety_path, None)
+        .map_err(|e| ProtimerError::Database(format!("Failed to save a copy of the current database: {}", e)))?;
 
-    Ok(invoices)
+    conn.restore(DatabaseName::Main, &path, None::<fn(rusqlite::backup::Progress)>)
+        .map_err(|e| ProtimerError::Database(format!("Failed to restore backup: {}", e)))?;
+
+    // Older backups predate later migrations; bring the restored schema up to date
+    init_db(&conn)?;
+    refresh_timezone_override(&conn);
+
+    Ok(())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // Ensure data directory exists
-    let data_dir = get_data_dir();
-    fs::create_dir_all(&data_dir).expect("Failed to create data directory");
-
-    // Initialize database
-    let db_path = get_db_path();
+    // Initialize database. Without a data directory there is nowhere to keep it,
+    // so this is the one place a missing home directory is still fatal.
+    let db_path = get_db_path().unwrap_or_else(|e| panic!("Failed to locate database: {}", e));
     let conn = Connection::open(&db_path).expect("Failed to open database");
+    conn.busy_timeout(Duration::from_secs(5)).expect("Failed to set busy timeout");
     init_db(&conn).expect("Failed to initialize database");
+    refresh_timezone_override(&conn);
+    match close_stale_manual_sessions(&conn, now_ms()) {
+        Ok(0) => {}
+        Ok(closed) => eprintln!("Closed {} manual session(s) left running while ProTimer was closed", closed),
+        Err(e) => eprintln!("Failed to close stale sessions: {}", e),
+    }
+    let readers = ReadPool::open(&db_path, READ_POOL_SIZE).expect("Failed to open read connections");
 
     let state = AppState {
         db: Mutex::new(conn),
+        readers,
         cache: Mutex::new(ActivityCache {
             entries: Arc::new(Vec::new()),
             file_modified: None,
+            read_offset: 0,
+            parse_failures: 0,
+            parse_failures_reported: false,
+        }),
+        idle_cache: Mutex::new(SystemIdleCache {
+            idle_ms: 0,
+            last_checked: 0,
         }),
     };
 
@@ -1368,28 +5876,80 @@ pub fn run() {
         .manage(state)
         .invoke_handler(tauri::generate_handler![
             get_projects,
+            get_projects_with_stats,
             create_project,
             update_project_rate,
+            update_project_color,
             update_project_name,
+            update_project_path,
+            update_project_client,
+            set_project_invoice_note,
+            set_project_goals,
+            set_project_archived,
+            set_project_match_parents,
             delete_project,
             start_tracking,
+            start_tracking_by_path,
             stop_tracking,
+            pause_tracking,
+            resume_tracking,
+            promote_to_manual,
+            list_stale_sessions,
+            discard_session,
+            set_pending_description,
+            reopen_entry,
             get_status,
+            reconcile_sessions,
+            get_project_status,
+            get_goals_status,
+            get_claude_tool_breakdown,
             get_entries,
+            search_entries,
             delete_entry,
+            restore_entry,
+            purge_deleted_entries,
             update_entry,
+            update_entry_description,
+            set_entry_tags,
+            set_entry_claude_flag,
+            merge_entries,
+            split_entry,
+            reassign_entries,
+            merge_projects,
             add_time_entry,
+            import_entries_csv,
             get_weekly_summary,
+            get_monthly_summary,
+            get_summary_for_range,
+            get_time_by_tag,
+            get_daily_totals,
+            get_hourly_distribution,
             get_data_path,
+            get_schema_version,
             open_data_folder,
             open_invoices_folder,
             open_file,
             check_hooks_installed,
+            get_app_info,
             install_hooks,
             get_business_info,
+            get_setting,
+            set_setting,
+            get_all_settings,
             save_business_info,
             generate_invoice,
+            generate_combined_invoice,
+            regenerate_invoice,
+            get_invoice_entries,
+            get_unbilled_total,
             get_invoices,
+            export_invoices_csv,
+            export_database_json,
+            import_database_json,
+            verify_integrity,
+            repair_integrity,
+            create_backup_now,
+            restore_backup,
         ])
         .setup(|app| {
             if cfg!(debug_assertions) {
@@ -1402,7 +5962,7 @@ pub fn run() {
 
             // Setup file watcher for activity log
             let app_handle = app.handle().clone();
-            let activity_log_path = get_activity_log_path();
+            let activity_log_path = get_activity_log_path()?;
 
             // Ensure the activity log file exists
             if !activity_log_path.exists() {
@@ -1420,29 +5980,242 @@ pub fn run() {
                     }
                 };
 
-                if let Err(e) = watcher.watch(&activity_log_path, RecursiveMode::NonRecursive) {
-                    eprintln!("Failed to watch activity log: {}", e);
+                // Watch the directory rather than the file: past 1000 lines append_activity_line
+                // writes the last 500 to a .jsonl.tmp file and renames it over the log, which
+                // replaces the inode and silently ends a file watch.
+                // The log sits directly in the data dir, so a non-recursive watch is enough
+                // and keeps invoice/backup writes out of the event stream.
+                let watch_dir = match activity_log_path.parent() {
+                    Some(dir) => dir.to_path_buf(),
+                    None => {
+                        eprintln!("Activity log has no parent directory");
+                        return;
+                    }
+                };
+                let log_file_name = activity_log_path.file_name().map(|n| n.to_os_string());
+
+                if let Err(e) = watcher.watch(&watch_dir, RecursiveMode::NonRecursive) {
+                    eprintln!("Failed to watch activity log directory: {}", e);
                     return;
                 }
 
+                // The hook appends a line per Claude event, so modifications arrive in bursts.
+                // Coalesce them and emit at most once per debounce window.
+                let mut pending = false;
+                let mut last_emit: Option<Instant> = None;
+
                 loop {
-                    match rx.recv() {
-                        Ok(Ok(Event { kind: EventKind::Modify(_), .. })) => {
-                            // Emit event to frontend when activity log is modified
-                            let _ = app_handle.emit("activity-log-changed", ());
+                    let timeout = if pending {
+                        last_emit.map_or(Duration::ZERO, |t| ACTIVITY_EVENT_DEBOUNCE.saturating_sub(t.elapsed()))
+                    } else {
+                        Duration::from_secs(3600)
+                    };
+
+                    match rx.recv_timeout(timeout) {
+                        // Appends arrive as Modify; a rotation lands as a rename (Modify) or Create
+                        Ok(Ok(Event { kind: EventKind::Modify(_) | EventKind::Create(_), paths, .. })) => {
+                            // Compare file names only; some backends report canonicalized parents
+                            if paths.iter().any(|p| p.file_name().map(|n| n.to_os_string()) == log_file_name) {
+                                pending = true;
+                            }
                         }
                         Ok(Err(e)) => eprintln!("Watch error: {:?}", e),
-                        Err(e) => {
-                            eprintln!("Channel error: {:?}", e);
+                        Err(RecvTimeoutError::Disconnected) => {
+                            eprintln!("Channel error: watcher disconnected");
                             break;
                         }
                         _ => {}
                     }
+
+                    let window_elapsed = last_emit.map_or(true, |t| t.elapsed() >= ACTIVITY_EVENT_DEBOUNCE);
+                    if pending && window_elapsed {
+                        // Emit event to frontend when activity log is modified
+                        let _ = app_handle.emit("activity-log-changed", ());
+                        push_status(&app_handle);
+                        last_emit = Some(Instant::now());
+                        pending = false;
+                    }
                 }
             });
 
+            // Heartbeat push between activity bursts
+            let status_handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(STATUS_PUSH_INTERVAL);
+                push_status(&status_handle);
+            });
+
+            let backup_handle = app.handle().clone();
+            std::thread::spawn(move || run_backup_loop(backup_handle));
+
             Ok(())
         })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory under the system temp dir, removed again by the test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("protimer-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn trailing_slashes_are_ignored() {
+        assert!(is_path_within_project("/a/b/", "/a/b", false));
+        assert!(is_path_within_project("/a/b", "/a/b/", false));
+        assert!(is_path_within_project("/a/b/c/", "/a/b/", false));
+    }
+
+    #[test]
+    fn sibling_with_shared_prefix_does_not_match() {
+        assert!(!is_path_within_project("/a/bc", "/a/b", false));
+        assert!(!is_path_within_project("/a/bc/d", "/a/b/", false));
+        assert!(!is_path_within_project("/a/b", "/a/bc", true));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_cwd_resolves_to_project() {
+        let root = temp_dir("symlink");
+        let project = root.join("work").join("app");
+        fs::create_dir_all(project.join("src")).unwrap();
+        let link = root.join("link");
+        std::os::unix::fs::symlink(&project, &link).unwrap();
+
+        let project_path = project.to_string_lossy().to_string();
+        assert!(is_path_within_project(&link.to_string_lossy(), &project_path, false));
+        assert!(is_path_within_project(&link.join("src").to_string_lossy(), &project_path, false));
+        // `..` segments resolve too
+        let dotted = project.join("src").join("..").to_string_lossy().to_string();
+        assert!(is_path_within_project(&dotted, &project_path, false));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    // Paths that don't exist are compared as written, so case always matters for them
+    #[test]
+    fn paths_not_on_disk_compare_case_sensitively() {
+        assert!(!is_path_within_project("/no/such/App", "/no/such/app", false));
+        assert!(is_path_within_project("/no/such/app/src", "/no/such/app", false));
+    }
+
+    // Linux filesystems are case-sensitive, so a differently cased cwd is another folder.
+    // macOS and Windows defaults are case-insensitive and resolve through canonicalize instead.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn differently_cased_cwd_is_another_folder_on_linux() {
+        let root = temp_dir("case");
+        let project = root.join("app");
+        fs::create_dir_all(&project).unwrap();
+
+        let upper = root.join("App").to_string_lossy().to_string();
+        assert!(!is_path_within_project(&upper, &project.to_string_lossy(), false));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn time_entry_for_missing_project_is_rejected() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+
+        let err = conn
+            .execute(
+                "INSERT INTO time_entries (id, projectId, startTime, endTime) VALUES ('e1', 'missing', 0, 1000)",
+                [],
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("FOREIGN KEY constraint failed"), "unexpected error: {}", err);
+
+        // The same entry goes in once its project exists
+        conn.execute(
+            "INSERT INTO projects (id, name, path, color, createdAt) VALUES ('p1', 'App', '/work/app', '#FF6B6B', 0)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO time_entries (id, projectId, startTime, endTime) VALUES ('e1', 'p1', 0, 1000)",
+            [],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn new_projects_get_the_least_used_palette_color() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        let add_project = |id: &str, color: &str| {
+            conn.execute(
+                "INSERT INTO projects (id, name, path, color, createdAt) VALUES (?1, ?1, ?2, ?3, 0)",
+                params![id, format!("/work/{}", id), color],
+            )
+            .unwrap();
+        };
+
+        // Unused colors are handed out in palette order, skipping ones already taken
+        assert_eq!(pick_project_color(&conn).unwrap(), "#FF6B6B");
+        add_project("a", "#FF6B6B");
+        add_project("b", "#45b7d1");
+        assert_eq!(pick_project_color(&conn).unwrap(), "#4ECDC4");
+
+        // With every color in use, the least used wins, and ties go to palette order
+        for (i, color) in DEFAULT_PROJECT_PALETTE.iter().enumerate() {
+            if *color != "#FF6B6B" && *color != "#45B7D1" {
+                add_project(&format!("c{}", i), color);
+            }
+        }
+        add_project("d1", "#4ECDC4");
+        add_project("d2", "#FF6B6B");
+        assert_eq!(pick_project_color(&conn).unwrap(), "#45B7D1");
+
+        // A custom palette replaces the default
+        store_setting(&conn, SETTING_PROJECT_PALETTE, Some("#4ECDC4, #123abc")).unwrap();
+        assert_eq!(pick_project_color(&conn).unwrap(), "#123ABC");
+    }
+
+    #[test]
+    fn hourly_distribution_splits_entries_at_hour_boundaries() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO projects (id, name, path, color, createdAt) VALUES ('p1', 'App', '/work/app', '#FF6B6B', 0)",
+            [],
+        )
+        .unwrap();
+
+        // Mid-January has no DST change in either hemisphere, so local hours are an hour apart
+        let at = |day: u32, hour: u32, minute: u32| {
+            let naive = chrono::NaiveDate::from_ymd_opt(2024, 1, day).unwrap().and_hms_opt(hour, minute, 0).unwrap();
+            local_datetime(naive).single().unwrap().timestamp_millis()
+        };
+        let minutes = |m: i64| m * 60_000;
+        let add_entry = |id: &str, start: i64, end: i64| {
+            conn.execute(
+                "INSERT INTO time_entries (id, projectId, startTime, endTime) VALUES (?1, 'p1', ?2, ?3)",
+                params![id, start, end],
+            )
+            .unwrap();
+        };
+        add_entry("late", at(15, 23, 30), at(16, 0, 45));
+        add_entry("long", at(15, 10, 15), at(15, 13, 5));
+
+        // The range ends at 00:30, so the entry crossing midnight is clipped there
+        let buckets = hourly_distribution(&conn, None, at(15, 0, 0), at(16, 0, 30)).unwrap();
+        let mut expected = [0i64; 24];
+        expected[23] = minutes(30);
+        expected[0] = minutes(30);
+        expected[10] = minutes(45);
+        expected[11] = minutes(60);
+        expected[12] = minutes(60);
+        expected[13] = minutes(5);
+        assert_eq!(buckets, expected);
+        assert_eq!(buckets.iter().sum::<i64>(), minutes(60 + 170));
+    }
+}
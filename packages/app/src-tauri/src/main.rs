@@ -2,5 +2,11 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+  // Claude Code hooks invoke `protimer hook` with the event payload on stdin
+  if std::env::args().nth(1).as_deref() == Some("hook") {
+    protimer_lib::run_hook();
+    return;
+  }
+
   protimer_lib::run();
 }
@@ -0,0 +1,125 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Sub};
+
+// Money as a whole number of cents, so sums of invoice lines are exact. Floating point only
+// appears at the edges: hours and rates come in as f64 and are rounded to a cent once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cents(pub i64);
+
+impl Cents {
+    // Nearest cent to a decimal amount (half away from zero)
+    pub fn from_amount(amount: f64) -> Self {
+        Cents((amount * 100.0).round() as i64)
+    }
+
+    // Price of `hours` at `rate` per hour, rounded once to the nearest cent
+    pub fn for_hours(hours: f64, rate: f64) -> Self {
+        Self::from_amount(hours * rate)
+    }
+
+    // `percent`% of this amount (e.g. tax), rounded to the nearest cent
+    pub fn percent(self, percent: f64) -> Self {
+        Cents((self.0 as f64 * percent / 100.0).round() as i64)
+    }
+
+    // Tax already contained in this gross amount at `percent`%: gross - gross / (1 + rate)
+    pub fn included_percent(self, percent: f64) -> Self {
+        let net = (self.0 as f64 / (1.0 + percent / 100.0)).round() as i64;
+        Cents(self.0 - net)
+    }
+
+    // Decimal amount for the frontend and the invoices table
+    pub fn as_amount(self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+}
+
+impl Add for Cents {
+    type Output = Cents;
+
+    fn add(self, other: Cents) -> Cents {
+        Cents(self.0 + other.0)
+    }
+}
+
+impl Sub for Cents {
+    type Output = Cents;
+
+    fn sub(self, other: Cents) -> Cents {
+        Cents(self.0 - other.0)
+    }
+}
+
+impl AddAssign for Cents {
+    fn add_assign(&mut self, other: Cents) {
+        self.0 += other.0;
+    }
+}
+
+impl Sum for Cents {
+    fn sum<I: Iterator<Item = Cents>>(iter: I) -> Cents {
+        iter.fold(Cents(0), Add::add)
+    }
+}
+
+// Plain decimal with two places and no float rounding, e.g. "1234.50" or "-0.05"
+impl fmt::Display for Cents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        write!(f, "{}{}.{:02}", sign, abs / 100, abs % 100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_cents_round_away_from_zero() {
+        assert_eq!(Cents::for_hours(0.5, 0.01), Cents(1));
+        assert_eq!(Cents::for_hours(1.25, 0.1), Cents(13));
+        assert_eq!(Cents(5).percent(10.0), Cents(1));
+        assert_eq!(Cents(15).percent(10.0), Cents(2));
+        // 105 / 1.1 = 95.45..., so the included tax is 10, not 9.55
+        assert_eq!(Cents(105).included_percent(10.0), Cents(10));
+        // Net 1 / 2 = 0.5 rounds up to 1, leaving no tax in a one-cent gross
+        assert_eq!(Cents(1).included_percent(100.0), Cents(0));
+    }
+
+    #[test]
+    fn negative_adjustments_round_symmetrically() {
+        assert_eq!(Cents::from_amount(-12.345), Cents(-1235));
+        assert_eq!(Cents::from_amount(-0.005), Cents(-1));
+        assert_eq!(Cents(-5).percent(10.0), Cents(-1));
+        assert_eq!(Cents::from_amount(-25.0) + Cents(10000), Cents(7500));
+        assert_eq!(Cents(-5).to_string(), "-0.05");
+    }
+
+    #[test]
+    fn included_tax_is_backed_out_of_the_gross() {
+        assert_eq!(Cents(11500).included_percent(15.0), Cents(1500));
+        assert_eq!(Cents(10000).included_percent(20.0), Cents(1667));
+        // What's left after backing out the tax is the net it was charged on
+        let gross = Cents(9999);
+        let tax = gross.included_percent(7.5);
+        assert_eq!(gross - tax, Cents(9301));
+        assert_eq!((gross - tax).percent(7.5), tax);
+    }
+
+    #[test]
+    fn rows_and_tax_add_up_to_the_total() {
+        let rows = [Cents::for_hours(1.33, 87.5), Cents::for_hours(2.17, 87.5), Cents::for_hours(0.08, 120.0)];
+        // 116.375 + 189.875 + 9.60 is 315.85 in floats, but the rendered rows are 116.38 + 189.88 + 9.60
+        let subtotal: Cents = rows.iter().copied().sum();
+        assert_eq!(subtotal, Cents(31586));
+
+        let tax = subtotal.percent(8.25);
+        let total = subtotal + tax;
+        assert_eq!(tax, Cents(2606));
+        assert_eq!(total, Cents(34192));
+        assert_eq!(total.to_string(), "341.92");
+        assert_eq!(total.as_amount(), 341.92);
+    }
+}
@@ -5,7 +5,6 @@ import { getCurrentWindow } from "@tauri-apps/api/window";
 import { listen } from "@tauri-apps/api/event";
 
 // Cleanup tracking for memory leak prevention
-let fetchDataIntervalId: number | null = null;
 let rafId: number | null = null;
 let isAppRunning = true;
 
@@ -24,14 +23,25 @@ interface Project {
   path: string;
   color: string;
   hourlyRate: number | null;
+  archived: boolean;
+  matchParents: boolean;
   isTracking: boolean;
+  isPaused: boolean;
   manualMode: boolean;
   elapsedTime: number;
   todayTime: number;
   weekTime: number;
   totalTime: number;
+  claudeTime: number;
   claudeState: "active" | "stopped";
   claudeSessionCount: number;
+  claudeSessions: ClaudeSession[];
+}
+
+interface ClaudeSession {
+  sessionId: string;
+  state: "active" | "stopped";
+  lastActivity: number;
 }
 
 interface BusinessInfo {
@@ -44,6 +54,7 @@ interface Status {
   projects: Project[];
   todayTotal: number;
   claudeTotal: number;
+  systemIdleTime: number;
 }
 
 interface TimeEntry {
@@ -51,6 +62,7 @@ interface TimeEntry {
   projectId: string;
   startTime: number;
   endTime: number;
+  tags: string[];
 }
 
 function formatDuration(ms: number, showSeconds = true): string {
@@ -82,6 +94,19 @@ function formatEarnings(ms: number, hourlyRate: number | null): string {
   return `$${amount.toFixed(2)}`;
 }
 
+// Commands reject with { kind, message } (see ProtimerError in lib.rs)
+interface CommandError {
+  kind: "notFound" | "validation" | "conflict" | "database" | "io" | "pdfGeneration";
+  message: string;
+}
+
+function errorMessage(err: unknown): string {
+  if (err && typeof err === "object" && "message" in err) {
+    return String((err as CommandError).message);
+  }
+  return String(err);
+}
+
 // Tauri invoke wrappers
 async function fetchStatus(): Promise<Status> {
   return invoke("get_status");
@@ -107,8 +132,8 @@ async function updateProjectName(projectId: string, name: string): Promise<void>
   await invoke("update_project_name", { projectId, name });
 }
 
-async function addProject(name: string, path: string): Promise<void> {
-  await invoke("create_project", { name, path });
+async function addProject(name: string, path: string, allowNested = false): Promise<void> {
+  await invoke("create_project", { name, path, allowNested });
 }
 
 async function getBusinessInfo(): Promise<BusinessInfo> {
@@ -123,12 +148,28 @@ async function saveBusinessInfo(info: BusinessInfo): Promise<void> {
   });
 }
 
-async function generateInvoice(projectId: string, startDate: number, endDate: number, extraHours: number): Promise<string> {
+interface InvoiceResult {
+  invoiceNumber: string;
+  filePath: string;
+  subtotal: number;
+  taxAmount: number;
+  total: number;
+  totalHours: number;
+  entryCount: number;
+}
+
+async function generateInvoice(projectId: string, startDate: number, endDate: number, extraHours: number): Promise<InvoiceResult> {
   return invoke("generate_invoice", { projectId, startDate, endDate, extraHours });
 }
 
+interface EntryPage {
+  entries: TimeEntry[];
+  totalCount: number;
+}
+
 async function fetchEntries(projectId: string, dayStart?: number): Promise<TimeEntry[]> {
-  return invoke("get_entries", { projectId, dayStart: dayStart ?? null });
+  const page: EntryPage = await invoke("get_entries", { projectId, dayStart: dayStart ?? null });
+  return page.entries;
 }
 
 async function deleteEntry(entryId: string): Promise<void> {
@@ -147,6 +188,7 @@ interface HooksStatus {
   scriptInstalled: boolean;
   settingsConfigured: boolean;
   fullyInstalled: boolean;
+  foreignHooks: string[];
 }
 
 async function checkHooksInstalled(): Promise<HooksStatus> {
@@ -271,7 +313,7 @@ function createProjectCard(p: Project): HTMLElement {
           await rebuildProjects();
         } catch (err) {
           console.error("Failed to delete project:", err);
-          alert(`Failed to delete project: ${err}`);
+          alert(`Failed to delete project: ${errorMessage(err)}`);
         }
       });
     });
@@ -474,7 +516,19 @@ function buildShell(): void {
     e.preventDefault();
     const nameInput = document.getElementById("project-name") as HTMLInputElement;
     const pathInput = document.getElementById("project-path") as HTMLInputElement;
-    await addProject(nameInput.value, pathInput.value);
+    try {
+      await addProject(nameInput.value, pathInput.value);
+    } catch (err) {
+      if ((err as CommandError).kind !== "conflict") {
+        alert(`Failed to add project: ${errorMessage(err)}`);
+        return;
+      }
+      // A folder inside (or containing) another project needs confirmation
+      if (!confirm(`${errorMessage(err)}\n\nAdd it anyway?`)) {
+        return;
+      }
+      await addProject(nameInput.value, pathInput.value, true);
+    }
     nameInput.value = "";
     pathInput.value = "";
     rebuildProjects();
@@ -508,67 +562,70 @@ async function rebuildProjects(): Promise<void> {
     document.getElementById("app")!.innerHTML = `
       <div class="error">
         <h2>Failed to load ProTimer</h2>
-        <p>Error: ${err}</p>
+        <p>Error: ${errorMessage(err)}</p>
       </div>
     `;
   }
 }
 
-// Fetch data from backend (runs every 5 seconds + on activity log changes)
+// Fetch data from backend on demand (e.g. after a user action); routine updates arrive via status-updated
 async function fetchData(): Promise<void> {
   try {
-    const status = await fetchStatus();
-    lastFetchTime = Date.now();
+    await applyStatus(await fetchStatus());
+  } catch {
+    // Ignore fetch errors
+  }
+}
 
-    // Override backend state with local manual mode state
-    for (const p of status.projects) {
-      const local = localManualMode.get(p.id);
-      if (local) {
-        // Local state is the source of truth
-        p.manualMode = local.active;
-        p.isTracking = local.active || p.claudeState === "active";
-
-        // Cleanup: Only remove local state once backend has caught up
-        if (!local.active && !p.manualMode && !p.isTracking) {
-          // If we have frozen times, wait for backend to catch up (within 1 second tolerance)
-          const frozen = frozenTimes.get(p.id);
-          if (frozen) {
-            const backendCaughtUp = Math.abs(p.weekTime - frozen.weekTime) < 1000;
-            if (backendCaughtUp) {
-              localManualMode.delete(p.id);
-              frozenTimes.delete(p.id);
-            }
-          } else {
-            // No frozen state, safe to cleanup
+async function applyStatus(status: Status): Promise<void> {
+  lastFetchTime = Date.now();
+
+  // Override backend state with local manual mode state
+  for (const p of status.projects) {
+    const local = localManualMode.get(p.id);
+    if (local) {
+      // Local state is the source of truth
+      p.manualMode = local.active;
+      p.isTracking = local.active || p.claudeState === "active";
+
+      // Cleanup: Only remove local state once backend has caught up
+      if (!local.active && !p.manualMode && !p.isTracking) {
+        // If we have frozen times, wait for backend to catch up (within 1 second tolerance)
+        const frozen = frozenTimes.get(p.id);
+        if (frozen) {
+          const backendCaughtUp = Math.abs(p.weekTime - frozen.weekTime) < 1000;
+          if (backendCaughtUp) {
             localManualMode.delete(p.id);
+            frozenTimes.delete(p.id);
           }
+        } else {
+          // No frozen state, safe to cleanup
+          localManualMode.delete(p.id);
         }
       }
     }
+  }
 
-    currentStatus = status;
-
-    // Check if projects changed
-    const currentIds = new Set(status.projects.map(p => p.id));
-    const sameProjects = currentIds.size === knownProjectIds.size &&
-      [...currentIds].every(id => knownProjectIds.has(id));
+  currentStatus = status;
 
-    if (!sameProjects) {
-      await rebuildProjects();
-      return;
-    }
+  // Check if projects changed
+  const currentIds = new Set(status.projects.map(p => p.id));
+  const sameProjects = currentIds.size === knownProjectIds.size &&
+    [...currentIds].every(id => knownProjectIds.has(id));
 
-    // Update non-timer elements
-    for (const p of status.projects) {
-      updateProjectCard(p);
-    }
+  if (!sameProjects) {
+    await rebuildProjects();
+    return;
+  }
 
-    // Update dock badge
-    const isTracking = status.projects.some(p => p.isTracking);
-    getCurrentWindow().setBadgeLabel(isTracking ? "●" : undefined).catch(() => {});
-  } catch {
-    // Ignore fetch errors
+  // Update non-timer elements
+  for (const p of status.projects) {
+    updateProjectCard(p);
   }
+
+  // Update dock badge
+  const isTracking = status.projects.some(p => p.isTracking);
+  getCurrentWindow().setBadgeLabel(isTracking ? "●" : undefined).catch(() => {});
 }
 
 // Render timers - runs at 60fps but only updates DOM once per second
@@ -738,7 +795,7 @@ async function showSettingsModal(): Promise<void> {
       await saveBusinessInfo({ name, email, taxRate });
       alert("Business information saved!");
     } catch (err) {
-      alert(`Failed to save: ${err}`);
+      alert(`Failed to save: ${errorMessage(err)}`);
     }
   });
 
@@ -761,7 +818,7 @@ function showHookSetupModal(): void {
         <p>ProTimer needs to setup Claude Code hooks to automatically track when Claude is working.</p>
         <p class="hook-setup-details">This will:</p>
         <ul>
-          <li>Create a hook script at <code>~/.protimer/hooks/</code></li>
+          <li>Register ProTimer as a Claude Code hook (no bash or jq required)</li>
           <li>Update your Claude settings at <code>~/.claude/settings.json</code></li>
         </ul>
         <p class="hook-setup-note">You can still use manual time tracking without hooks.</p>
@@ -792,6 +849,9 @@ function showHookSetupModal(): void {
     try {
       const status = await installHooks();
       if (status.fullyInstalled) {
+        if (status.foreignHooks.length > 0) {
+          console.warn("Kept existing Claude Code hooks from other tools:", status.foreignHooks);
+        }
         btn.textContent = "Installed!";
         timeoutIds.push(window.setTimeout(closeModal, 1000));
       } else {
@@ -1408,13 +1468,13 @@ function showInvoiceDialog(project: Project): void {
     const endMs = new Date(endYear, endMonth - 1, endDay, 23, 59, 59, 999).getTime();
 
     try {
-      const pdfPath = await generateInvoice(project.id, startMs, endMs, extraHours);
+      const result = await generateInvoice(project.id, startMs, endMs, extraHours);
       // Auto-open the generated invoice
-      await invoke("open_file", { filePath: pdfPath });
-      alert(`Invoice generated and opened!\n\nSaved to: ${pdfPath}`);
+      await invoke("open_file", { filePath: result.filePath });
+      alert(`Invoice ${result.invoiceNumber} generated and opened!\n\nTotal: ${result.total.toFixed(2)} (${result.totalHours.toFixed(2)} hours)\nSaved to: ${result.filePath}`);
       closeDialog();
     } catch (err) {
-      alert(`Failed to generate invoice: ${err}`);
+      alert(`Failed to generate invoice: ${errorMessage(err)}`);
     }
   });
   overlay.addEventListener("click", (e) => {
@@ -1426,10 +1486,6 @@ function showInvoiceDialog(project: Project): void {
 // Cleanup function for window unload
 function cleanup(): void {
   isAppRunning = false;
-  if (fetchDataIntervalId !== null) {
-    clearInterval(fetchDataIntervalId);
-    fetchDataIntervalId = null;
-  }
   if (rafId !== null) {
     cancelAnimationFrame(rafId);
     rafId = null;
@@ -1441,13 +1497,15 @@ buildShell();
 rebuildProjects();
 checkAndShowHookSetup();
 
-// Listen for activity log changes from the file watcher
-listen("activity-log-changed", () => {
-  fetchData();
+// The backend pushes a fresh status after activity log changes and every few seconds
+listen<Status>("status-updated", (event) => {
+  applyStatus(event.payload);
 });
 
-// Fetch data every 5 seconds (reduced from 1 second since we have file watcher)
-fetchDataIntervalId = window.setInterval(fetchData, 5000);
+// Sent once per run when the activity log has lines the backend can't parse
+listen<number>("activity-log-parse-failures", (event) => {
+  console.warn(`${event.payload} activity log line(s) could not be parsed; Claude activity may be missing`);
+});
 
 // Render timers at 60fps (updates DOM once per second)
 rafId = requestAnimationFrame(renderTimers);